@@ -1,6 +1,10 @@
-use bevy::{app::ScheduleRunnerPlugin, asset::weak_handle, log::LogPlugin, prelude::*};
+use bevy::{
+    app::ScheduleRunnerPlugin, asset::weak_handle, ecs::component::ComponentId, log::LogPlugin,
+    prelude::*, state::app::StatesPlugin,
+};
 
 use bevy_def::*;
+use serde::Deserialize;
 use std::time::Duration;
 
 fn main() {
@@ -10,21 +14,46 @@ fn main() {
         MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_millis(200))),
         LogPlugin::default(),
         AssetPlugin::default(),
-        DefPlugin::<Stat>::default(),
+        StatesPlugin,
+        DefPlugin::<Stat>::default()
+            .with_loader(&["stat.ron"], &["stat.json"])
+            .with_file_loader(&["stats.ron"], &["stats.json"])
+            .with_folder("defs")
+            .with_loading_state(AppState::Loading, AppState::Running),
+        DefPlugin::<Skill>::default()
+            .with_file_loader(&["skills.ron"], &[])
+            .with_folder("skills")
+            .with_links::<Stat>()
+            .with_loading_state(AppState::Loading, AppState::Running),
     ));
 
+    app.init_state::<AppState>();
     app.init_resource::<Status>();
+    app.register_type::<Stat>();
 
     app.add_systems(Startup, startup);
-    app.add_systems(Update, (show, spawn, increment_health));
+    app.add_systems(OnEnter(AppState::Running), (spawn, show_skills));
+    app.add_systems(
+        Update,
+        (show, increment_health).run_if(in_state(AppState::Running)),
+    );
+    app.add_systems(Update, ready);
 
     app.run();
 }
 
+#[derive(States, Clone, PartialEq, Eq, Hash, Debug, Default)]
+enum AppState {
+    #[default]
+    Loading,
+    Running,
+}
+
 #[derive(Resource, Default)]
 struct Status {
-    spawned: bool,
     handle: Option<Handle<StatAsset>>,
+    files: Vec<Handle<StatAsset>>,
+    core: Handle<DefFile<StatAsset>>,
 }
 
 #[derive(Component)]
@@ -32,7 +61,12 @@ struct MarkerComponent;
 
 const HEALTH: Handle<StatAsset> = weak_handle!("aa0c572f-1ebb-4f8b-b5c6-cfd8651799f2");
 
-fn startup(mut stat_assets: ResMut<Assets<StatAsset>>, mut status: ResMut<Status>) {
+fn startup(
+    asset_server: Res<AssetServer>,
+    mut stat_assets: ResMut<Assets<StatAsset>>,
+    mut index: ResMut<DefIndex<Stat>>,
+    mut status: ResMut<Status>,
+) {
     info!("startup");
 
     stat_assets.insert(
@@ -53,46 +87,50 @@ fn startup(mut stat_assets: ResMut<Assets<StatAsset>>, mut status: ResMut<Status
     });
 
     status.handle = Some(handle);
+    status.files = vec![
+        asset_server.load("defs/stamina.stat.ron"),
+        asset_server.load("defs/luck.stat.json"),
+    ];
+    status.core = asset_server.load("defs/core.stats.ron");
+
+    // The loading state waits for these too.
+    for handle in &status.files {
+        index.request(handle);
+    }
 }
 
-fn spawn(mut commands: Commands, mut status: ResMut<Status>, index: Res<DefIndex<Stat>>) {
+fn spawn(mut commands: Commands) {
     info!("spawn");
 
-    if !status.spawned {
-        let Some(health_id) = index.asset_to_id().get(&HEALTH.id()).copied() else {
-            return;
-        };
+    // Every def is registered in this state, so nothing has to be deferred.
+    commands.spawn(MarkerComponent);
 
-        let Some(mana_id) = index.find_by_name("mana").map(|(_, id)| id) else {
-            return;
-        };
-
-        commands.spawn(MarkerComponent);
+    commands
+        .spawn(MarkerComponent)
+        .queue(InsertDef::by_asset(&HEALTH, Stat { current: 35.0 }));
 
-        commands
-            .spawn(MarkerComponent)
-            .queue(InsertDef::new(health_id, Stat { current: 35.0 }));
+    commands
+        .spawn(MarkerComponent)
+        .queue(InsertDef::<Stat>::from_asset("mana"));
 
-        commands
-            .spawn(MarkerComponent)
-            .queue(InsertDef::new(mana_id, Stat { current: 15.0 }));
+    commands
+        .spawn(MarkerComponent)
+        .queue(InsertDef::by_asset(&HEALTH, Stat { current: 35.0 }))
+        .queue(InsertDef::by_name("mana", Stat { current: 15.0 }));
 
-        commands
-            .spawn(MarkerComponent)
-            .queue(InsertDef::new(health_id, Stat { current: 35.0 }))
-            .queue(InsertDef::new(mana_id, Stat { current: 15.0 }));
-
-        status.spawned = true;
-    }
+    commands
+        .spawn(MarkerComponent)
+        .queue(InsertDef::<Stat>::from_asset("stamina"));
 }
 
 fn show(query: Query<(NameOrEntity, DefEntityRef<Stat>), With<MarkerComponent>>) {
     for (print, item) in query {
         let hp = item.get_ref(&HEALTH);
         let mp = item.find_ref("mana");
+        let sp = item.find_ref("stamina");
 
-        if hp.is_none() && mp.is_none() {
-            info!("{print} no hp, no mp");
+        if hp.is_none() && mp.is_none() && sp.is_none() {
+            info!("{print} no stats");
         } else {
             if let Some(hp) = hp {
                 info!(
@@ -107,36 +145,91 @@ fn show(query: Query<(NameOrEntity, DefEntityRef<Stat>), With<MarkerComponent>>)
                     mp.value.current, mp.asset.minimal, mp.asset.maximal
                 );
             }
+
+            if let Some(sp) = sp {
+                info!(
+                    "{print} sp: {} [{} .. {}]",
+                    sp.value.current, sp.asset.minimal, sp.asset.maximal
+                );
+            }
         }
     }
 }
 
-fn increment_health(query: Query<DefEntityMut<Stat>, With<MarkerComponent>>) {
-    for mut stats in query {
-        if let Some(mut hp) = stats.get_mut(&HEALTH) {
-            hp.value.current += 0.2;
-            hp.value.current = hp.value.current.clamp(hp.asset.minimal, hp.asset.maximal);
+fn ready(mut events: EventReader<DefFolderReady<Stat>>, index: Res<DefIndex<Stat>>) {
+    for event in events.read() {
+        info!("{} is ready, {} defs", event.path, index.names().len());
+    }
+}
+
+fn show_skills(skills: DefParam<Skill>, stats: DefParam<Stat>) {
+    for (name, &(id, _)) in skills.index.names() {
+        let Some((_, skill)) = skills.asset(id) else {
+            continue;
+        };
+
+        match stats.get(&skill.governing_stat) {
+            Ok((_, stat)) => info!(
+                "skill {name} is governed by {} [{} .. {}]",
+                stat.defname, stat.minimal, stat.maximal
+            ),
+            Err(err) => warn!("skill {name}: {err}"),
         }
     }
 }
 
-#[derive(Reflect, Debug)]
+struct Health;
+
+impl DefSelector<Stat> for Health {
+    fn select(index: &DefIndex<Stat>) -> Option<ComponentId> {
+        index.asset_to_id().get(&HEALTH.id()).copied()
+    }
+}
+
+fn increment_health(mut health: DefQueryMut<Stat, Health>) {
+    for (_, mut hp) in health.iter_mut() {
+        hp.value.current += 0.2;
+        hp.value.current = hp.value.current.clamp(hp.asset.minimal, hp.asset.maximal);
+    }
+}
+
+#[derive(DefComponent, Reflect, Debug)]
+#[reflect(DefComponent)]
+#[def(asset = StatAsset, defname = defname)]
 pub struct Stat {
     pub current: f32,
 }
 
-unsafe impl DefComponent for Stat {
-    type Asset = StatAsset;
-
-    fn defname(asset: &Self::Asset) -> std::borrow::Cow<'static, str> {
-        asset.defname.clone().into()
+impl DefDefault for Stat {
+    fn from_asset(asset: &Self::Asset) -> Self {
+        Self {
+            current: asset.default,
+        }
     }
 }
 
-#[derive(Asset, Reflect, Debug)]
+#[derive(Asset, Reflect, Debug, Deserialize)]
 pub struct StatAsset {
     pub defname: String,
     pub default: f32,
     pub minimal: f32,
     pub maximal: f32,
 }
+
+#[derive(DefComponent)]
+#[def(asset = SkillAsset, defname = defname)]
+pub struct Skill {
+    pub level: u32,
+}
+
+impl DefLinks<Stat> for Skill {
+    fn links(asset: &Self::Asset) -> Vec<&DefLink<Stat>> {
+        vec![&asset.governing_stat]
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Deserialize)]
+pub struct SkillAsset {
+    pub defname: String,
+    pub governing_stat: DefLink<Stat>,
+}
@@ -0,0 +1,183 @@
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::{DeriveInput, ExprPath, Ident, LitStr, Result, Type, parse_macro_input};
+
+const HOOKS: [&str; 5] = [
+    "on_add",
+    "on_insert",
+    "on_replace",
+    "on_remove",
+    "on_despawn",
+];
+
+/// Implements `DefComponent`.
+///
+/// ```ignore
+/// #[derive(DefComponent)]
+/// #[def(asset = StatAsset, defname = defname, storage = "SparseSet", on_add = stat_added)]
+/// pub struct Stat {
+///     pub current: f32,
+/// }
+/// ```
+///
+/// - `asset`: the asset type, required.
+/// - `defname`: the asset field that holds the name of the def, required.
+/// - `key`: the namespace of `DefKey`s, defaults to the name of the struct.
+/// - `storage`: `"Table"` (default) or `"SparseSet"`.
+/// - `on_add`, `on_insert`, `on_replace`, `on_remove`, `on_despawn`: hook functions.
+/// - `clone`: `"Clone"`, `"Reflect"` or `"Ignore"` (default), see `DefCloneBehavior`.
+/// - `map_entities`: a `fn(&mut Self, &mut impl EntityMapper)` that maps entities in the value.
+#[proc_macro_derive(DefComponent, attributes(def))]
+pub fn derive_def_component(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    derive(input)
+        .unwrap_or_else(|err| err.to_compile_error())
+        .into()
+}
+
+#[derive(Default)]
+struct Attrs {
+    asset: Option<Type>,
+    defname: Option<Ident>,
+    key: Option<LitStr>,
+    storage: Option<Ident>,
+    hooks: Vec<(Ident, ExprPath)>,
+    map_entities: Option<ExprPath>,
+    clone: Option<TokenStream2>,
+}
+
+fn parse_attrs(input: &DeriveInput) -> Result<Attrs> {
+    let mut attrs = Attrs::default();
+
+    for attr in input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("def"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("asset") {
+                attrs.asset = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("defname") {
+                attrs.defname = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("key") {
+                attrs.key = Some(meta.value()?.parse()?);
+            } else if meta.path.is_ident("storage") {
+                let storage: LitStr = meta.value()?.parse()?;
+                match storage.value().as_str() {
+                    "Table" | "SparseSet" => {
+                        attrs.storage = Some(Ident::new(&storage.value(), storage.span()));
+                    }
+                    _ => {
+                        return Err(meta.error("expected `\"Table\"` or `\"SparseSet\"`"));
+                    }
+                }
+            } else if meta.path.is_ident("clone") {
+                let clone: LitStr = meta.value()?.parse()?;
+                attrs.clone = Some(match clone.value().as_str() {
+                    "Clone" => quote! { ::bevy_def::DefCloneBehavior::clone() },
+                    "Reflect" => quote! { ::bevy_def::DefCloneBehavior::reflect() },
+                    "Ignore" => quote! { ::bevy_def::DefCloneBehavior::Ignore },
+                    _ => {
+                        return Err(
+                            meta.error("expected `\"Clone\"`, `\"Reflect\"` or `\"Ignore\"`")
+                        );
+                    }
+                });
+            } else if meta.path.is_ident("map_entities") {
+                attrs.map_entities = Some(meta.value()?.parse()?);
+            } else if let Some(hook) = HOOKS.iter().find(|hook| meta.path.is_ident(hook)) {
+                let name = Ident::new(hook, proc_macro2::Span::call_site());
+                attrs.hooks.push((name, meta.value()?.parse()?));
+            } else {
+                return Err(meta.error("unknown `def` attribute"));
+            }
+            Ok(())
+        })?;
+    }
+
+    Ok(attrs)
+}
+
+fn derive(input: DeriveInput) -> Result<TokenStream2> {
+    let attrs = parse_attrs(&input)?;
+    let ident = &input.ident;
+
+    let asset = attrs
+        .asset
+        .ok_or_else(|| syn::Error::new_spanned(ident, "missing `#[def(asset = ...)]` attribute"))?;
+    let defname = attrs.defname.ok_or_else(|| {
+        syn::Error::new_spanned(ident, "missing `#[def(defname = ...)]` attribute")
+    })?;
+
+    // Unlike `type_name`, the declared name doesn't change with the module path or compiler.
+    let key = attrs
+        .key
+        .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+
+    let storage = attrs.storage.map(|storage| {
+        quote! {
+            const STORAGE_TYPE: ::bevy_def::__macro_exports::StorageType =
+                ::bevy_def::__macro_exports::StorageType::#storage;
+        }
+    });
+
+    let hooks = attrs.hooks.iter().map(|(name, path)| {
+        quote! {
+            fn #name() -> ::core::option::Option<::bevy_def::__macro_exports::ComponentHook> {
+                ::core::option::Option::Some(#path)
+            }
+        }
+    });
+
+    let map_entities = attrs.map_entities.map(|path| {
+        quote! {
+            fn map_entities<E: ::bevy_def::__macro_exports::EntityMapper>(this: &mut Self, mapper: &mut E) {
+                #path(this, mapper)
+            }
+        }
+    });
+
+    let clone = attrs.clone.map(|clone| {
+        quote! {
+            fn clone_behavior() -> ::bevy_def::DefCloneBehavior<Self> {
+                #clone
+            }
+        }
+    });
+
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    Ok(quote! {
+        impl #impl_generics ::bevy_def::DefComponent for #ident #ty_generics #where_clause {
+            type Asset = #asset;
+
+            #storage
+
+            fn defname(asset: &Self::Asset) -> ::std::borrow::Cow<'static, str> {
+                ::std::borrow::Cow::Owned(::std::string::ToString::to_string(&asset.#defname))
+            }
+
+            fn key_namespace() -> &'static str {
+                #key
+            }
+
+            #(#hooks)*
+
+            #map_entities
+
+            #clone
+        }
+
+        const _: () = {
+            #[allow(dead_code)]
+            fn assert_def_component #impl_generics () #where_clause {
+                fn is_sized_send_sync<T: Sized + Send + Sync + 'static>() {}
+                fn is_asset<A: ::bevy_def::__macro_exports::Asset>() {}
+
+                is_sized_send_sync::<#ident #ty_generics>();
+                is_asset::<#asset>();
+            }
+        };
+    })
+}
@@ -0,0 +1,69 @@
+use crate::DefComponent;
+use bevy_ecs::{
+    component::{ComponentCloneBehavior, ComponentCloneFn},
+    entity::{ComponentCloneCtx, SourceComponent},
+};
+use bevy_ptr::OwningPtr;
+use bevy_reflect::Reflect;
+
+/// How [`EntityCloner`](bevy_ecs::entity::EntityCloner) copies def components of type `T`.
+///
+/// Def components are registered without type information, so Bevy's own
+/// `Clone` and `Reflect` based handlers can't be used for them.
+pub enum DefCloneBehavior<T: DefComponent> {
+    /// Def components are left out of the clone.
+    Ignore,
+    /// Copies the value with a `Clone`-like function, see [`DefCloneBehavior::clone`].
+    Clone(fn(&T) -> T),
+    /// Copies the value through reflection, see [`DefCloneBehavior::reflect`].
+    /// Values that can't be cloned are left out.
+    Reflect(fn(&T) -> Option<T>),
+    /// A handler that writes the target component itself.
+    Custom(ComponentCloneFn),
+}
+
+impl<T: DefComponent> DefCloneBehavior<T> {
+    pub fn clone() -> Self
+    where
+        T: Clone,
+    {
+        Self::Clone(T::clone)
+    }
+
+    pub fn reflect() -> Self
+    where
+        T: Reflect,
+    {
+        Self::Reflect(|value| value.reflect_clone().ok()?.take().ok())
+    }
+
+    pub(crate) fn component_clone_behavior(&self) -> ComponentCloneBehavior {
+        match *self {
+            Self::Ignore => ComponentCloneBehavior::Ignore,
+            Self::Clone(_) | Self::Reflect(_) => ComponentCloneBehavior::Custom(clone_def::<T>),
+            Self::Custom(clone) => ComponentCloneBehavior::Custom(clone),
+        }
+    }
+}
+
+fn clone_def<T: DefComponent>(source: &SourceComponent, ctx: &mut ComponentCloneCtx) {
+    // SAFETY: only used as the clone handler of components registered by `DefIndex<T>`.
+    let value = unsafe { source.ptr().deref::<T>() };
+
+    let cloned = match T::clone_behavior() {
+        DefCloneBehavior::Clone(clone) => Some(clone(value)),
+        DefCloneBehavior::Reflect(clone) => clone(value),
+        DefCloneBehavior::Ignore | DefCloneBehavior::Custom(_) => None,
+    };
+
+    let Some(mut cloned) = cloned else {
+        return;
+    };
+
+    T::map_entities(&mut cloned, &mut ctx.entity_mapper());
+
+    OwningPtr::make(cloned, |ptr| {
+        // SAFETY: the component stores `T`, and the moved out value is owned by the target.
+        unsafe { ctx.write_target_component_ptr(ptr.as_ref()) }
+    });
+}
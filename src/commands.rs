@@ -0,0 +1,231 @@
+use crate::{DefComponent, DefDefault, DefError, DefIndex, DefKey};
+use bevy_asset::{AssetId, Assets};
+use bevy_ecs::{
+    component::ComponentId,
+    entity::Entity,
+    system::EntityCommand,
+    world::{EntityWorldMut, World},
+};
+use bevy_ptr::OwningPtr;
+use std::{any::type_name, borrow::Cow};
+
+/// The def a command works with, resolved through [`DefIndex`] when the command is applied.
+pub(crate) enum DefTarget<T: DefComponent> {
+    Component(ComponentId),
+    Name(Cow<'static, str>),
+    Asset(AssetId<T::Asset>),
+    Key(DefKey),
+}
+
+impl<T: DefComponent> DefTarget<T> {
+    fn resolve(&self, world: &World) -> Result<ComponentId, DefError> {
+        let index = world
+            .get_resource::<DefIndex<T>>()
+            .ok_or(DefError::MissingIndex(type_name::<T>()))?;
+
+        self.resolve_in(index).ok_or_else(|| match *self {
+            Self::Component(component_id) => DefError::UnknownComponent {
+                ty: type_name::<T>(),
+                component_id,
+            },
+            Self::Name(ref name) => DefError::UnknownName {
+                ty: type_name::<T>(),
+                name: name.clone(),
+            },
+            Self::Asset(id) => DefError::UnknownAsset {
+                ty: type_name::<T>(),
+                id: id.untyped(),
+            },
+            Self::Key(key) => DefError::UnknownKey {
+                ty: type_name::<T>(),
+                key,
+            },
+        })
+    }
+
+    /// Only ids registered by `index` are returned, so the component is known to store `T`.
+    pub(crate) fn resolve_in(&self, index: &DefIndex<T>) -> Option<ComponentId> {
+        match self {
+            &Self::Component(component_id) => index
+                .contains_component(component_id)
+                .then_some(component_id),
+            Self::Name(name) => index
+                .find_by_name(name.as_ref())
+                .map(|(_, component_id)| component_id),
+            Self::Asset(id) => index.asset_to_id().get(id).copied(),
+            &Self::Key(key) => index.find_by_key(key).map(|(_, component_id)| component_id),
+        }
+    }
+}
+
+/// The value an [`InsertDef`] inserts, either given up front or built from the def asset.
+pub(crate) enum DefValue<T: DefComponent> {
+    Value(T),
+    FromAsset(fn(&T::Asset) -> T),
+}
+
+impl<T: DefComponent> DefValue<T> {
+    pub(crate) fn make(
+        self,
+        world: &World,
+        component_id: ComponentId,
+        asset_id: Option<AssetId<T::Asset>>,
+    ) -> Result<T, DefError> {
+        match self {
+            Self::Value(value) => Ok(value),
+            Self::FromAsset(make) => asset_id
+                .and_then(|id| world.get_resource::<Assets<T::Asset>>()?.get(id))
+                .map(make)
+                .ok_or(DefError::MissingAsset {
+                    ty: type_name::<T>(),
+                    component_id,
+                }),
+        }
+    }
+}
+
+/// An [`InsertDef::deferred`] insertion parked in [`DefIndex`] until its def is registered.
+pub(crate) struct QueuedDef<T: DefComponent> {
+    pub(crate) entity: Entity,
+    pub(crate) target: DefTarget<T>,
+    pub(crate) value: DefValue<T>,
+}
+
+pub(crate) fn insert_by_id<T: DefComponent>(
+    entity: &mut EntityWorldMut<'_>,
+    component_id: ComponentId,
+    value: T,
+) {
+    // SAFETY: callers only pass ids resolved through `DefIndex<T>`, which store `T`.
+    OwningPtr::make(value, |component| unsafe {
+        entity.insert_by_id(component_id, component);
+    });
+}
+
+pub struct InsertDef<T: DefComponent> {
+    target: DefTarget<T>,
+    value: DefValue<T>,
+    deferred: bool,
+}
+
+impl<T: DefComponent> InsertDef<T> {
+    pub const fn new(component_id: ComponentId, value: T) -> Self {
+        Self {
+            target: DefTarget::Component(component_id),
+            value: DefValue::Value(value),
+            deferred: false,
+        }
+    }
+
+    pub fn by_name(name: impl Into<Cow<'static, str>>, value: T) -> Self {
+        Self {
+            target: DefTarget::Name(name.into()),
+            value: DefValue::Value(value),
+            deferred: false,
+        }
+    }
+
+    pub fn by_asset(id: impl Into<AssetId<T::Asset>>, value: T) -> Self {
+        Self {
+            target: DefTarget::Asset(id.into()),
+            value: DefValue::Value(value),
+            deferred: false,
+        }
+    }
+
+    pub fn by_key(key: DefKey, value: T) -> Self {
+        Self {
+            target: DefTarget::Key(key),
+            value: DefValue::Value(value),
+            deferred: false,
+        }
+    }
+
+    /// Builds the value with [`DefDefault::from_asset`] when the command is applied.
+    pub fn from_asset(name: impl Into<Cow<'static, str>>) -> Self
+    where
+        T: DefDefault,
+    {
+        Self {
+            target: DefTarget::Name(name.into()),
+            value: DefValue::FromAsset(T::from_asset),
+            deferred: false,
+        }
+    }
+
+    /// If the def isn't registered yet, queue the insertion in [`DefIndex`]
+    /// and apply it once the def arrives instead of failing.
+    pub fn deferred(mut self) -> Self {
+        self.deferred = true;
+        self
+    }
+}
+
+impl<T: DefComponent> EntityCommand<Result<(), DefError>> for InsertDef<T> {
+    fn apply(self, mut entity: EntityWorldMut<'_>) -> Result<(), DefError> {
+        match self.target.resolve(entity.world()) {
+            Ok(component_id) => {
+                let world = entity.world();
+                let asset_id = world
+                    .get_resource::<DefIndex<T>>()
+                    .and_then(|index| index.id_to_asset().get(&component_id).copied());
+                let value = self.value.make(world, component_id, asset_id)?;
+                insert_by_id(&mut entity, component_id, value);
+            }
+            Err(
+                DefError::UnknownName { .. }
+                | DefError::UnknownAsset { .. }
+                | DefError::UnknownKey { .. },
+            ) if self.deferred => {
+                let queued = QueuedDef {
+                    entity: entity.id(),
+                    target: self.target,
+                    value: self.value,
+                };
+                entity.world_scope(|world| {
+                    world.resource_mut::<DefIndex<T>>().queued.push(queued);
+                });
+            }
+            Err(err) => return Err(err),
+        }
+        Ok(())
+    }
+}
+
+pub struct RemoveDef<T: DefComponent> {
+    target: DefTarget<T>,
+}
+
+impl<T: DefComponent> RemoveDef<T> {
+    pub const fn new(component_id: ComponentId) -> Self {
+        Self {
+            target: DefTarget::Component(component_id),
+        }
+    }
+
+    pub fn by_name(name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            target: DefTarget::Name(name.into()),
+        }
+    }
+
+    pub fn by_asset(id: impl Into<AssetId<T::Asset>>) -> Self {
+        Self {
+            target: DefTarget::Asset(id.into()),
+        }
+    }
+
+    pub fn by_key(key: DefKey) -> Self {
+        Self {
+            target: DefTarget::Key(key),
+        }
+    }
+}
+
+impl<T: DefComponent> EntityCommand<Result<(), DefError>> for RemoveDef<T> {
+    fn apply(self, mut entity: EntityWorldMut<'_>) -> Result<(), DefError> {
+        let component_id = self.target.resolve(entity.world())?;
+        entity.remove_by_id(component_id);
+        Ok(())
+    }
+}
@@ -1,6 +1,6 @@
 use crate::{
     DefComponent, DefEntityRef, DefIndex, DefMut, DefQueryState, DefRef,
-    debug::debug_checked_unwrap_result,
+    debug::{debug_checked_unwrap_option, debug_checked_unwrap_result},
 };
 use bevy_asset::{AssetId, Assets};
 use bevy_ecs::{
@@ -23,6 +23,7 @@ pub struct DefEntityMut<'w, T: DefComponent> {
     entity: UnsafeEntityCell<'w>,
     asset: &'w Assets<<T as DefComponent>::Asset>,
     index: &'w DefIndex<T>,
+    claimed: usize,
 }
 
 impl<'w, T: DefComponent> DefEntityMut<'w, T> {
@@ -42,51 +43,117 @@ impl<'w, T: DefComponent> DefEntityMut<'w, T> {
         self.index.id_to_asset.get(&id).copied()
     }
 
-    pub fn find_ref<Q>(&self, name: &Q) -> Option<DefRef<'w, '_, T>>
+    /// `None` if the def isn't registered, its asset is gone, or the entity doesn't have it.
+    pub fn find_ref<Q>(&self, name: &Q) -> Option<DefRef<'_, '_, T>>
     where
         Q: Hash + Eq + ?Sized,
         Cow<'static, str>: Borrow<Q>,
     {
         let (asset_id, component_id) = self.find(name)?;
         let asset = self.asset.get(asset_id)?;
+        // SAFETY: the id comes from the index, and `&self` prevents mutable borrows.
         let value = unsafe { self.value_ref(component_id)? };
         Some(DefRef { value, asset })
     }
 
-    pub fn find_mut<Q>(&mut self, name: &Q) -> Option<DefMut<'w, '_, T>>
+    /// `None` if the def isn't registered, its asset is gone, or the entity doesn't have it.
+    pub fn find_mut<Q>(&mut self, name: &Q) -> Option<DefMut<'_, '_, T>>
     where
         Q: Hash + Eq + ?Sized,
         Cow<'static, str>: Borrow<Q>,
     {
         let (asset_id, component_id) = self.find(name)?;
         let asset = self.asset.get(asset_id)?;
+        // SAFETY: the id comes from the index, and `&mut self` prevents other borrows.
         let value = unsafe { self.value_mut(component_id)? };
         Some(DefMut { value, asset })
     }
 
-    pub fn get_ref(&self, id: impl Into<AssetId<T::Asset>>) -> Option<DefRef<'w, '_, T>> {
-        let asset_id = id.into();
-        let component_id = self.index.asset_to_id.get(&asset_id).copied()?;
-        let asset = self.asset.get(asset_id)?;
+    /// `None` if the def isn't registered, its asset is gone, or the entity doesn't have it.
+    pub fn get_ref(&self, id: impl Into<AssetId<T::Asset>>) -> Option<DefRef<'_, '_, T>> {
+        let component_id = self.index.asset_to_id.get(&id.into()).copied()?;
+        let asset = self.asset.get(self.asset_id(component_id)?)?;
+        // SAFETY: the id comes from the index, and `&self` prevents mutable borrows.
         let value = unsafe { self.value_ref(component_id)? };
         Some(DefRef { value, asset })
     }
 
-    pub fn get_mut(&mut self, id: impl Into<AssetId<T::Asset>>) -> Option<DefMut<'w, '_, T>> {
-        let asset_id = id.into();
-        let component_id = self.index.asset_to_id.get(&asset_id).copied()?;
-        let asset = self.asset.get(asset_id)?;
+    /// `None` if the def isn't registered, its asset is gone, or the entity doesn't have it.
+    pub fn get_mut(&mut self, id: impl Into<AssetId<T::Asset>>) -> Option<DefMut<'_, '_, T>> {
+        let component_id = self.index.asset_to_id.get(&id.into()).copied()?;
+        let asset = self.asset.get(self.asset_id(component_id)?)?;
+        // SAFETY: the id comes from the index, and `&mut self` prevents other borrows.
         let value = unsafe { self.value_mut(component_id)? };
         Some(DefMut { value, asset })
     }
 
+    /// Iterates over every def of type `T` on this entity.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (AssetId<T::Asset>, ComponentId, DefRef<'_, '_, T>)> {
+        self.entity
+            .archetype()
+            .components()
+            .filter_map(|component_id| {
+                let asset_id = self.asset_id(component_id)?;
+                let asset = self.asset.get(asset_id)?;
+                // SAFETY: the id has an asset in the index.
+                let value = unsafe { self.value_ref(component_id)? };
+                Some((asset_id, component_id, DefRef { value, asset }))
+            })
+    }
+
+    /// Iterates mutably over every def of type `T` on this entity.
+    pub fn iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (AssetId<T::Asset>, ComponentId, DefMut<'_, '_, T>)> {
+        self.entity
+            .archetype()
+            .components()
+            .filter_map(|component_id| {
+                let asset_id = self.asset_id(component_id)?;
+                let asset = self.asset.get(asset_id)?;
+                // SAFETY: the id has an asset in the index, and every component
+                // of the archetype is visited once, so the values don't alias.
+                let value = unsafe { self.value_mut(component_id)? };
+                Some((asset_id, component_id, DefMut { value, asset }))
+            })
+    }
+
     pub fn asset(&self, id: impl Into<AssetId<T::Asset>>) -> Option<&'_ T::Asset> {
         self.asset.get(id)
     }
 
+    /// Gets the value of the def component `id`, orphaned ones included.
+    /// Returns `None` if `id` wasn't registered by [`DefIndex<T>`] or the entity doesn't have it.
+    pub fn checked_value_ref(&self, id: ComponentId) -> Option<&'_ T> {
+        if self.index.contains_component(id) {
+            // SAFETY: checked above, and `&self` keeps `value_mut` from running meanwhile.
+            unsafe { self.value_ref(id) }
+        } else {
+            None
+        }
+    }
+
+    /// Mutable version of [`Self::checked_value_ref`].
+    pub fn checked_value_mut(&mut self, id: ComponentId) -> Option<Mut<'_, T>> {
+        if self.index.contains_component(id) {
+            // SAFETY: checked above, and `&mut self` prevents other borrows.
+            unsafe { self.value_mut(id) }
+        } else {
+            None
+        }
+    }
+
     /// # Safety
-    /// no
-    pub unsafe fn value_ref(&self, id: ComponentId) -> Option<&'w T> {
+    /// - `id` must be registered by [`DefIndex<T>`], see [`DefIndex::contains_component`].
+    /// - No mutable reference to the same value may be alive while the result is used.
+    pub(crate) unsafe fn value_ref(&self, id: ComponentId) -> Option<&'w T> {
+        if !self.index.is_claimed(id, self.claimed) {
+            return None;
+        }
+        // SAFETY: mutable access to the claimed def components has been registered,
+        // the component stores `T` and isn't borrowed mutably, see the contract above.
         unsafe {
             let ptr = self.entity.get_by_id(id)?;
             Some(ptr.deref())
@@ -94,8 +161,14 @@ impl<'w, T: DefComponent> DefEntityMut<'w, T> {
     }
 
     /// # Safety
-    /// no
-    pub unsafe fn value_mut(&self, id: ComponentId) -> Option<Mut<'w, T>> {
+    /// - `id` must be registered by [`DefIndex<T>`], see [`DefIndex::contains_component`].
+    /// - No other reference to the same value may be alive while the result is used.
+    pub(crate) unsafe fn value_mut(&self, id: ComponentId) -> Option<Mut<'w, T>> {
+        if !self.index.is_claimed(id, self.claimed) {
+            return None;
+        }
+        // SAFETY: mutable access to the claimed def components has been registered,
+        // the component stores `T` and isn't borrowed elsewhere, see the contract above.
         unsafe {
             let ptr = self.entity.get_mut_by_id(id).ok()?;
             Some(ptr.with_type())
@@ -105,7 +178,12 @@ impl<'w, T: DefComponent> DefEntityMut<'w, T> {
 
 /// SAFETY: The accesses of `Self::ReadOnly` are a subset of the accesses of `Self`
 unsafe impl<'a, T: DefComponent> WorldQuery for DefEntityMut<'a, T> {
-    type Fetch<'w> = (UnsafeWorldCell<'w>, &'w DefIndex<T>, &'w Assets<T::Asset>);
+    type Fetch<'w> = (
+        UnsafeWorldCell<'w>,
+        &'w DefIndex<T>,
+        &'w Assets<T::Asset>,
+        usize,
+    );
     type State = DefQueryState;
 
     fn shrink_fetch<'wlong: 'wshort, 'wshort>(fetch: Self::Fetch<'wlong>) -> Self::Fetch<'wshort> {
@@ -120,15 +198,15 @@ unsafe impl<'a, T: DefComponent> WorldQuery for DefEntityMut<'a, T> {
         _last_run: Tick,
         _this_run: Tick,
     ) -> Self::Fetch<'w> {
-        // let access = Access::default();
-        // // access.read_all_components();
-        // (world, access)
-
+        // SAFETY: read access to both resources has been registered and `init_state` created them.
         unsafe {
-            let index = world.get_resource_by_id(state.index_id).unwrap().deref();
-            let asset = world.get_resource_by_id(state.asset_id).unwrap().deref();
+            let index = world.get_resource_by_id(state.index_id);
+            let asset = world.get_resource_by_id(state.asset_id);
 
-            (world, index, asset)
+            let index = debug_checked_unwrap_option(index).deref();
+            let asset = debug_checked_unwrap_option(asset).deref();
+
+            (world, index, asset, state.claimed)
         }
     }
 
@@ -139,42 +217,28 @@ unsafe impl<'a, T: DefComponent> WorldQuery for DefEntityMut<'a, T> {
         _: &'w Archetype,
         _table: &Table,
     ) {
-        // fetch.1.clone_from(state.access());
     }
 
     #[inline]
-    unsafe fn set_table<'w>(fetch: &mut Self::Fetch<'w>, state: &Self::State, _: &'w Table) {
-        // fetch.1.clone_from(state.access());
-    }
-
-    #[inline]
-    fn set_access<'w>(state: &mut Self::State, access: &FilteredAccess<ComponentId>) {
-        // state.clone_from(access);
-        // state.access_mut().clear_writes();
-    }
+    unsafe fn set_table<'w>(_fetch: &mut Self::Fetch<'w>, _state: &Self::State, _: &'w Table) {}
 
     fn update_component_access(
-        _state: &Self::State,
-        _filtered_access: &mut FilteredAccess<ComponentId>,
+        state: &Self::State,
+        filtered_access: &mut FilteredAccess<ComponentId>,
     ) {
-        // assert!(
-        //     filtered_access.access().is_compatible(state.access()),
-        //     "DefRef conflicts with a previous access in this query. Exclusive access cannot coincide with any other accesses.",
-        // );
-
-        // filtered_access.access_mut().extend(state.access());
+        assert!(
+            filtered_access.access().is_compatible(&state.access),
+            "DefEntityMut conflicts with a previous access in this query. Exclusive access cannot coincide with any other accesses.",
+        );
+        filtered_access.access_mut().extend(&state.access);
     }
 
     fn init_state(world: &mut World) -> Self::State {
-        let asset_id = world.init_resource::<Assets<T::Asset>>();
-        let index_id = world.init_resource::<DefIndex<T>>();
-        DefQueryState { asset_id, index_id }
+        DefQueryState::init::<T>(world, true)
     }
 
     fn get_state(components: &Components) -> Option<Self::State> {
-        let asset_id = components.resource_id::<Assets<T::Asset>>()?;
-        let index_id = components.resource_id::<DefIndex<T>>()?;
-        Some(DefQueryState { asset_id, index_id })
+        DefQueryState::get::<T>(components, true)
     }
 
     fn matches_component_set(
@@ -198,18 +262,19 @@ unsafe impl<'a, T: DefComponent> QueryData for DefEntityMut<'a, T> {
 
     #[inline(always)]
     unsafe fn fetch<'w>(
-        (world, index, asset): &mut Self::Fetch<'w>,
+        (world, index, asset, claimed): &mut Self::Fetch<'w>,
         entity: Entity,
         _table_row: TableRow,
     ) -> Self::Item<'w> {
         // SAFETY: `fetch` must be called with an entity that exists in the world
         let entity = unsafe { debug_checked_unwrap_result(world.get_entity(entity)) };
 
-        // SAFETY: mutable access to every component has been registered.
+        // SAFETY: mutable access to the claimed def components has been registered.
         DefEntityMut {
             entity,
             index,
             asset,
+            claimed: *claimed,
         }
     }
 }
@@ -1,4 +1,7 @@
-use crate::{DefComponent, DefIndex, DefQueryState, DefRef, debug::debug_checked_unwrap_result};
+use crate::{
+    DefComponent, DefIndex, DefQueryState, DefRef,
+    debug::{debug_checked_unwrap_option, debug_checked_unwrap_result},
+};
 use bevy_asset::{AssetId, Assets};
 use bevy_ecs::{
     archetype::Archetype,
@@ -20,6 +23,7 @@ pub struct DefEntityRef<'w, T: DefComponent> {
     entity: UnsafeEntityCell<'w>,
     asset: &'w Assets<<T as DefComponent>::Asset>,
     index: &'w DefIndex<T>,
+    claimed: usize,
 }
 
 impl<'w, T: DefComponent> DefEntityRef<'w, T> {
@@ -39,6 +43,7 @@ impl<'w, T: DefComponent> DefEntityRef<'w, T> {
         self.index.id_to_asset.get(&id).copied()
     }
 
+    /// `None` if the def isn't registered, its asset is gone, or the entity doesn't have it.
     pub fn find_ref<Q>(&self, name: &Q) -> Option<DefRef<'w, '_, T>>
     where
         Q: Hash + Eq + ?Sized,
@@ -46,24 +51,45 @@ impl<'w, T: DefComponent> DefEntityRef<'w, T> {
     {
         let (asset_id, component_id) = self.find(name)?;
         let asset = self.asset.get(asset_id)?;
+        // SAFETY: the id comes from the index.
         let value = unsafe { self.value_ref(component_id)? };
         Some(DefRef { value, asset })
     }
 
+    /// `None` if the def isn't registered, its asset is gone, or the entity doesn't have it.
     pub fn get_ref(&self, id: impl Into<AssetId<T::Asset>>) -> Option<DefRef<'w, '_, T>> {
-        let asset_id = id.into();
-        let component_id = self.index.asset_to_id.get(&asset_id).copied()?;
-        let asset = self.asset.get(asset_id)?;
+        let component_id = self.index.asset_to_id.get(&id.into()).copied()?;
+        let asset = self.asset.get(self.asset_id(component_id)?)?;
+        // SAFETY: the id comes from the index.
         let value = unsafe { self.value_ref(component_id)? };
         Some(DefRef { value, asset })
     }
 
+    /// Iterates over every def of type `T` on this entity.
+    pub fn iter(
+        &self,
+    ) -> impl Iterator<Item = (AssetId<T::Asset>, ComponentId, DefRef<'w, '_, T>)> {
+        self.entity
+            .archetype()
+            .components()
+            .filter_map(|component_id| {
+                let asset_id = self.asset_id(component_id)?;
+                let asset = self.asset.get(asset_id)?;
+                // SAFETY: the id has an asset in the index.
+                let value = unsafe { self.value_ref(component_id)? };
+                Some((asset_id, component_id, DefRef { value, asset }))
+            })
+    }
+
     pub fn asset(&self, id: impl Into<AssetId<T::Asset>>) -> Option<&'_ T::Asset> {
         self.asset.get(id)
     }
 
+    /// Gets the value of the def component `id`, orphaned ones included.
+    /// Returns `None` if `id` wasn't registered by [`DefIndex<T>`] or the entity doesn't have it.
     pub fn checked_value_ref(&self, id: ComponentId) -> Option<&'w T> {
-        if self.index.id_to_asset.contains_key(&id) {
+        if self.index.contains_component(id) {
+            // SAFETY: checked above.
             unsafe { self.value_ref(id) }
         } else {
             None
@@ -71,8 +97,13 @@ impl<'w, T: DefComponent> DefEntityRef<'w, T> {
     }
 
     /// # Safety
-    /// no
-    pub unsafe fn value_ref(&self, id: ComponentId) -> Option<&'w T> {
+    /// `id` must be registered by [`DefIndex<T>`], see [`DefIndex::contains_component`].
+    pub(crate) unsafe fn value_ref(&self, id: ComponentId) -> Option<&'w T> {
+        if !self.index.is_claimed(id, self.claimed) {
+            return None;
+        }
+        // SAFETY: read access to the claimed def components has been registered,
+        // and the component stores `T`, see the contract above.
         unsafe {
             let ptr = self.entity.get_by_id(id)?;
             Some(ptr.deref())
@@ -82,7 +113,12 @@ impl<'w, T: DefComponent> DefEntityRef<'w, T> {
 
 /// SAFETY: The accesses of `Self::ReadOnly` are a subset of the accesses of `Self`
 unsafe impl<'a, T: DefComponent> WorldQuery for DefEntityRef<'a, T> {
-    type Fetch<'w> = (UnsafeWorldCell<'w>, &'w DefIndex<T>, &'w Assets<T::Asset>);
+    type Fetch<'w> = (
+        UnsafeWorldCell<'w>,
+        &'w DefIndex<T>,
+        &'w Assets<T::Asset>,
+        usize,
+    );
     type State = DefQueryState;
 
     fn shrink_fetch<'wlong: 'wshort, 'wshort>(fetch: Self::Fetch<'wlong>) -> Self::Fetch<'wshort> {
@@ -97,15 +133,15 @@ unsafe impl<'a, T: DefComponent> WorldQuery for DefEntityRef<'a, T> {
         _last_run: Tick,
         _this_run: Tick,
     ) -> Self::Fetch<'w> {
-        // let access = Access::default();
-        // // access.read_all_components();
-        // (world, access)
-
+        // SAFETY: read access to both resources has been registered and `init_state` created them.
         unsafe {
-            let index = world.get_resource_by_id(state.index_id).unwrap().deref();
-            let asset = world.get_resource_by_id(state.asset_id).unwrap().deref();
+            let index = world.get_resource_by_id(state.index_id);
+            let asset = world.get_resource_by_id(state.asset_id);
+
+            let index = debug_checked_unwrap_option(index).deref();
+            let asset = debug_checked_unwrap_option(asset).deref();
 
-            (world, index, asset)
+            (world, index, asset, state.claimed)
         }
     }
 
@@ -116,42 +152,28 @@ unsafe impl<'a, T: DefComponent> WorldQuery for DefEntityRef<'a, T> {
         _: &'w Archetype,
         _table: &Table,
     ) {
-        // fetch.1.clone_from(state.access());
     }
 
     #[inline]
-    unsafe fn set_table<'w>(fetch: &mut Self::Fetch<'w>, state: &Self::State, _: &'w Table) {
-        // fetch.1.clone_from(state.access());
-    }
-
-    #[inline]
-    fn set_access<'w>(state: &mut Self::State, access: &FilteredAccess<ComponentId>) {
-        // state.clone_from(access);
-        // state.access_mut().clear_writes();
-    }
+    unsafe fn set_table<'w>(_fetch: &mut Self::Fetch<'w>, _state: &Self::State, _: &'w Table) {}
 
     fn update_component_access(
-        _state: &Self::State,
-        _filtered_access: &mut FilteredAccess<ComponentId>,
+        state: &Self::State,
+        filtered_access: &mut FilteredAccess<ComponentId>,
     ) {
-        // assert!(
-        //     filtered_access.access().is_compatible(state.access()),
-        //     "DefRef conflicts with a previous access in this query. Exclusive access cannot coincide with any other accesses.",
-        // );
-
-        // filtered_access.access_mut().extend(state.access());
+        assert!(
+            filtered_access.access().is_compatible(&state.access),
+            "DefEntityRef conflicts with a previous access in this query. Shared access cannot coincide with exclusive access.",
+        );
+        filtered_access.access_mut().extend(&state.access);
     }
 
     fn init_state(world: &mut World) -> Self::State {
-        let asset_id = world.init_resource::<Assets<T::Asset>>();
-        let index_id = world.init_resource::<DefIndex<T>>();
-        DefQueryState { asset_id, index_id }
+        DefQueryState::init::<T>(world, false)
     }
 
     fn get_state(components: &Components) -> Option<Self::State> {
-        let asset_id = components.resource_id::<Assets<T::Asset>>()?;
-        let index_id = components.resource_id::<DefIndex<T>>()?;
-        Some(DefQueryState { asset_id, index_id })
+        DefQueryState::get::<T>(components, false)
     }
 
     fn matches_component_set(
@@ -175,18 +197,19 @@ unsafe impl<'a, T: DefComponent> QueryData for DefEntityRef<'a, T> {
 
     #[inline(always)]
     unsafe fn fetch<'w>(
-        (world, index, asset): &mut Self::Fetch<'w>,
+        (world, index, asset, claimed): &mut Self::Fetch<'w>,
         entity: Entity,
         _table_row: TableRow,
     ) -> Self::Item<'w> {
         // SAFETY: `fetch` must be called with an entity that exists in the world
         let entity = unsafe { debug_checked_unwrap_result(world.get_entity(entity)) };
 
-        // SAFETY: mutable access to every component has been registered.
+        // SAFETY: read access to the claimed def components has been registered.
         DefEntityRef {
             entity,
             index,
             asset,
+            claimed: *claimed,
         }
     }
 }
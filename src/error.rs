@@ -0,0 +1,54 @@
+use crate::DefKey;
+use bevy_asset::UntypedAssetId;
+use bevy_ecs::component::ComponentId;
+use std::borrow::Cow;
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum DefError {
+    #[error("`DefIndex<{0}>` doesn't exist, is `DefPlugin<{0}>` added?")]
+    MissingIndex(&'static str),
+    #[error("def `{name}` of type `{ty}` isn't registered")]
+    UnknownName {
+        ty: &'static str,
+        name: Cow<'static, str>,
+    },
+    #[error("{component_id:?} isn't a def component of type `{ty}`")]
+    UnknownComponent {
+        ty: &'static str,
+        component_id: ComponentId,
+    },
+    #[error("can't build a def value of type `{ty}` from reflection")]
+    FromReflect { ty: &'static str },
+    #[error("def asset {id} of type `{ty}` isn't registered")]
+    UnknownAsset {
+        ty: &'static str,
+        id: UntypedAssetId,
+    },
+    #[error("def key {key} of type `{ty}` isn't registered")]
+    UnknownKey { ty: &'static str, key: DefKey },
+    #[error("def inheritance cycle in `{ty}`: {}", .chain.join(" -> "))]
+    InheritanceCycle {
+        ty: &'static str,
+        chain: Vec<Cow<'static, str>>,
+    },
+    #[error("parent of def `{}` of type `{ty}` isn't registered: {}", .chain[0], .chain.join(" -> "))]
+    MissingParent {
+        ty: &'static str,
+        chain: Vec<Cow<'static, str>>,
+    },
+    #[error(
+        "def `{from}` of type `{from_ty}` links to `{name}` of type `{ty}`, which isn't registered"
+    )]
+    DanglingLink {
+        from_ty: &'static str,
+        from: Cow<'static, str>,
+        ty: &'static str,
+        name: Cow<'static, str>,
+    },
+    #[error("def {component_id:?} of type `{ty}` has no asset")]
+    MissingAsset {
+        ty: &'static str,
+        component_id: ComponentId,
+    },
+}
@@ -0,0 +1,295 @@
+use crate::{DefComponent, DefIndex, DefQueryState, debug::debug_checked_unwrap_option};
+use bevy_ecs::{
+    archetype::Archetype,
+    component::{ComponentId, ComponentTicks, Components, Tick},
+    entity::Entity,
+    query::{FilteredAccess, QueryFilter, WorldQuery},
+    storage::{Table, TableRow},
+    world::{World, unsafe_world_cell::UnsafeWorldCell},
+};
+use std::{any::TypeId, marker::PhantomData};
+
+/// Picks the def a filter works with.
+///
+/// If the def is registered when the query is built, the selector runs once and
+/// the query only visits archetypes that match it, like `With` and `Without` do.
+/// Otherwise it runs every time the query is fetched until the def arrives,
+/// so it can name a def that is registered long after the system was built.
+///
+/// A def that is only known at runtime can be picked with [`DefIndex::pick`]
+/// and selected with [`DefPicked`].
+///
+/// ```ignore
+/// struct Health;
+///
+/// impl DefSelector<Stat> for Health {
+///     fn select(index: &DefIndex<Stat>) -> Option<ComponentId> {
+///         index.find_by_name("health").map(|(_, id)| id)
+///     }
+/// }
+///
+/// fn regen(query: Query<DefEntityMut<Stat>, DefWith<Stat, Health>>) {}
+/// ```
+pub trait DefSelector<T: DefComponent>: Send + Sync + 'static {
+    fn select(index: &DefIndex<T>) -> Option<ComponentId>;
+}
+
+/// Selects the def picked with [`DefIndex::pick`] for the marker `M`.
+///
+/// Like any selector, the pick is read when the query is built, or on every fetch
+/// until there is one, so pick the def before building the systems that use it.
+///
+/// ```ignore
+/// struct Target;
+///
+/// let (_, id) = index.find_by_name(&config.target).unwrap();
+/// index.pick::<Target>(id);
+///
+/// fn highlight(query: Query<Entity, DefChanged<Stat, DefPicked<Target>>>) {}
+/// ```
+pub struct DefPicked<M: 'static>(PhantomData<fn() -> M>);
+
+impl<T: DefComponent, M: 'static> DefSelector<T> for DefPicked<M> {
+    fn select(index: &DefIndex<T>) -> Option<ComponentId> {
+        index.picked::<M>()
+    }
+}
+
+impl<T: DefComponent> DefIndex<T> {
+    /// Picks the def that [`DefPicked<M>`] selects.
+    pub fn pick<M: 'static>(&mut self, id: ComponentId) {
+        self.picked.insert(TypeId::of::<M>(), id);
+    }
+
+    pub fn unpick<M: 'static>(&mut self) -> Option<ComponentId> {
+        self.picked.remove(&TypeId::of::<M>())
+    }
+
+    pub fn picked<M: 'static>(&self) -> Option<ComponentId> {
+        self.picked.get(&TypeId::of::<M>()).copied()
+    }
+}
+
+pub struct DefFilterState {
+    query: DefQueryState,
+    /// The def picked when the query was built, if it was registered then.
+    selected: Option<ComponentId>,
+}
+
+impl DefFilterState {
+    fn init<T: DefComponent, S: DefSelector<T>>(world: &mut World) -> Self {
+        let query = DefQueryState::init::<T>(world, false);
+        let selected = select::<T, S>(world.resource::<DefIndex<T>>(), query.claimed);
+        Self { query, selected }
+    }
+
+    fn get<T: DefComponent>(components: &Components) -> Option<Self> {
+        Some(Self {
+            query: DefQueryState::get::<T>(components, false)?,
+            selected: None,
+        })
+    }
+
+    pub fn selected(&self) -> Option<ComponentId> {
+        self.selected
+    }
+}
+
+/// Runs `S` and drops ids that aren't claimed def components of `T`,
+/// since the filter has no access to anything else.
+fn select<T: DefComponent, S: DefSelector<T>>(
+    index: &DefIndex<T>,
+    claimed: usize,
+) -> Option<ComponentId> {
+    S::select(index).filter(|&id| index.contains_component(id) && index.is_claimed(id, claimed))
+}
+
+#[derive(Clone)]
+pub struct DefFilterFetch<'w> {
+    world: UnsafeWorldCell<'w>,
+    component_id: Option<ComponentId>,
+    matches: bool,
+    last_run: Tick,
+    this_run: Tick,
+}
+
+/// Filter that selects entities with the def picked by `S`.
+pub struct DefWith<T: DefComponent, S: DefSelector<T>>(PhantomData<fn() -> (T, S)>);
+
+/// Filter that selects entities without the def picked by `S`.
+pub struct DefWithout<T: DefComponent, S: DefSelector<T>>(PhantomData<fn() -> (T, S)>);
+
+/// Filter that selects entities whose def picked by `S` was added since the last run.
+pub struct DefAdded<T: DefComponent, S: DefSelector<T>>(PhantomData<fn() -> (T, S)>);
+
+/// Filter that selects entities whose def picked by `S` was added or mutably
+/// dereferenced since the last run.
+pub struct DefChanged<T: DefComponent, S: DefSelector<T>>(PhantomData<fn() -> (T, S)>);
+
+macro_rules! impl_def_filter {
+    (
+        $name:ident,
+        reads: $reads:literal,
+        $(narrow: $narrow:ident,)?
+        |$archetype:ident, $id:ident| $contains:expr,
+        |$fetch:ident, $entity:ident| $filter:expr
+    ) => {
+        /// SAFETY: `init_fetch` only reads `DefIndex<T>`, which is registered as a resource read.
+        /// Filters that look at change ticks register read access to every claimed def component,
+        /// and `select` drops anything else. `matches_component_set` and the `with` and `without`
+        /// filters only rely on the def picked up front, which the fetch keeps using.
+        unsafe impl<T: DefComponent, S: DefSelector<T>> WorldQuery for $name<T, S> {
+            type Fetch<'w> = DefFilterFetch<'w>;
+            type State = DefFilterState;
+
+            fn shrink_fetch<'wlong: 'wshort, 'wshort>(
+                fetch: Self::Fetch<'wlong>,
+            ) -> Self::Fetch<'wshort> {
+                fetch
+            }
+
+            const IS_DENSE: bool = false;
+
+            unsafe fn init_fetch<'w>(
+                world: UnsafeWorldCell<'w>,
+                state: &Self::State,
+                last_run: Tick,
+                this_run: Tick,
+            ) -> Self::Fetch<'w> {
+                // SAFETY: read access to the index has been registered and `init_state` created it.
+                let index = unsafe {
+                    let index = world.get_resource_by_id(state.query.index_id);
+                    debug_checked_unwrap_option(index).deref::<DefIndex<T>>()
+                };
+
+                DefFilterFetch {
+                    world,
+                    component_id: state
+                        .selected
+                        .or_else(|| select::<T, S>(index, state.query.claimed)),
+                    matches: false,
+                    last_run,
+                    this_run,
+                }
+            }
+
+            #[inline]
+            unsafe fn set_archetype<'w>(
+                fetch: &mut Self::Fetch<'w>,
+                _state: &Self::State,
+                $archetype: &'w Archetype,
+                _table: &'w Table,
+            ) {
+                let $archetype = |id| $archetype.contains(id);
+                let $id = fetch.component_id;
+                fetch.matches = $contains;
+            }
+
+            #[inline]
+            unsafe fn set_table<'w>(
+                _fetch: &mut Self::Fetch<'w>,
+                _state: &Self::State,
+                _table: &'w Table,
+            ) {
+            }
+
+            fn update_component_access(
+                state: &Self::State,
+                filtered_access: &mut FilteredAccess<ComponentId>,
+            ) {
+                if $reads {
+                    assert!(
+                        filtered_access.access().is_compatible(&state.query.access),
+                        "{} conflicts with a previous access in this query. Shared access cannot coincide with exclusive access.",
+                        stringify!($name),
+                    );
+                    filtered_access.access_mut().extend(&state.query.access);
+                } else {
+                    filtered_access
+                        .access_mut()
+                        .add_resource_read(state.query.index_id);
+                }
+
+                $(if let Some(id) = state.selected {
+                    filtered_access.$narrow(id);
+                })?
+            }
+
+            fn init_state(world: &mut World) -> Self::State {
+                DefFilterState::init::<T, S>(world)
+            }
+
+            fn get_state(components: &Components) -> Option<Self::State> {
+                DefFilterState::get::<T>(components)
+            }
+
+            fn matches_component_set(
+                state: &Self::State,
+                set_contains_id: &impl Fn(ComponentId) -> bool,
+            ) -> bool {
+                // Without a def picked up front, it's only known once the query is
+                // fetched, see `set_archetype`.
+                let $archetype = set_contains_id;
+                let $id = state.selected;
+                state.selected.is_none() || $contains
+            }
+        }
+
+        /// SAFETY: `filter_fetch` only reads change ticks of def components,
+        /// which are covered by `update_component_access`.
+        unsafe impl<T: DefComponent, S: DefSelector<T>> QueryFilter for $name<T, S> {
+            const IS_ARCHETYPAL: bool = false;
+
+            #[inline(always)]
+            unsafe fn filter_fetch(
+                $fetch: &mut Self::Fetch<'_>,
+                $entity: Entity,
+                _table_row: TableRow,
+            ) -> bool {
+                $fetch.matches && $filter
+            }
+        }
+    };
+}
+
+impl_def_filter!(
+    DefWith,
+    reads: false,
+    narrow: and_with,
+    |contains, id| id.is_some_and(contains),
+    |_fetch, _entity| true
+);
+
+impl_def_filter!(
+    DefWithout,
+    reads: false,
+    narrow: and_without,
+    |contains, id| id.is_none_or(|id| !contains(id)),
+    |_fetch, _entity| true
+);
+
+impl_def_filter!(
+    DefAdded,
+    reads: true,
+    |contains, id| id.is_some_and(contains),
+    |fetch, entity| unsafe { change_ticks(fetch, entity) }
+        .is_some_and(|ticks| ticks.is_added(fetch.last_run, fetch.this_run))
+);
+
+impl_def_filter!(
+    DefChanged,
+    reads: true,
+    |contains, id| id.is_some_and(contains),
+    |fetch, entity| unsafe { change_ticks(fetch, entity) }
+        .is_some_and(|ticks| ticks.is_changed(fetch.last_run, fetch.this_run))
+);
+
+/// # Safety
+/// Read access to the selected def component must be registered.
+#[inline(always)]
+unsafe fn change_ticks(fetch: &DefFilterFetch<'_>, entity: Entity) -> Option<ComponentTicks> {
+    let id = fetch.component_id?;
+    let entity = fetch.world.get_entity(entity).ok()?;
+    // SAFETY: guaranteed by the caller.
+    unsafe { entity.get_change_ticks_by_id(id) }
+}
@@ -0,0 +1,144 @@
+use crate::{DefComponent, DefFile, DefIndex};
+use bevy_asset::{AssetId, AssetServer, Assets, Handle, LoadedFolder};
+use bevy_ecs::{
+    event::{Event, EventWriter},
+    resource::Resource,
+    system::{Res, ResMut},
+};
+use bevy_log::info;
+use std::marker::PhantomData;
+
+/// Folders of def assets of type `T`, loaded with
+/// [`DefPlugin::with_folder`](crate::DefPlugin::with_folder) or [`DefFolders::load`].
+///
+/// A folder is ready once every def asset in it, including the defs of [`DefFile`]s,
+/// is indexed by [`DefIndex<T>`], see [`DefIndex::is_indexed`]. [`DefFolderReady<T>`]
+/// is sent once when that happens.
+#[derive(Resource)]
+pub struct DefFolders<T: DefComponent> {
+    folders: Vec<DefFolder<T>>,
+}
+
+impl<T: DefComponent> Default for DefFolders<T> {
+    fn default() -> Self {
+        Self {
+            folders: Vec::new(),
+        }
+    }
+}
+
+impl<T: DefComponent> DefFolders<T> {
+    pub fn load(&mut self, asset_server: &AssetServer, path: impl Into<String>) {
+        let path = path.into();
+        let handle = asset_server.load_folder(path.clone());
+
+        self.folders.push(DefFolder {
+            path,
+            handle,
+            assets: None,
+            registered: 0,
+            ready: false,
+        });
+    }
+
+    pub fn get(&self, path: &str) -> Option<&DefFolder<T>> {
+        self.folders.iter().find(|folder| folder.path == path)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &DefFolder<T>> {
+        self.folders.iter()
+    }
+
+    /// Whether every loaded folder is ready.
+    pub fn is_ready(&self) -> bool {
+        self.folders.iter().all(DefFolder::is_ready)
+    }
+}
+
+pub struct DefFolder<T: DefComponent> {
+    path: String,
+    handle: Handle<LoadedFolder>,
+    /// The def assets in the folder, once it's loaded.
+    assets: Option<Vec<AssetId<T::Asset>>>,
+    registered: usize,
+    ready: bool,
+}
+
+impl<T: DefComponent> DefFolder<T> {
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    pub fn handle(&self) -> &Handle<LoadedFolder> {
+        &self.handle
+    }
+
+    /// Def assets in the folder that aren't indexed yet, `None` while the folder is loading.
+    pub fn pending(&self) -> Option<usize> {
+        Some(self.assets.as_ref()?.len() - self.registered)
+    }
+
+    /// Whether the folder and the def files in it are loaded, even if not every def is indexed.
+    pub fn is_loaded(&self) -> bool {
+        self.assets.is_some()
+    }
+
+    pub fn registered(&self) -> usize {
+        self.registered
+    }
+
+    pub fn is_ready(&self) -> bool {
+        self.ready
+    }
+}
+
+/// Sent once when every def asset of a folder in [`DefFolders<T>`] is indexed.
+#[derive(Event)]
+pub struct DefFolderReady<T: DefComponent> {
+    pub path: String,
+    marker: PhantomData<fn() -> T>,
+}
+
+pub fn def_folder_system<T: DefComponent>(
+    mut folders: ResMut<DefFolders<T>>,
+    index: Res<DefIndex<T>>,
+    asset_server: Res<AssetServer>,
+    loaded_folders: Res<Assets<LoadedFolder>>,
+    files: Option<Res<Assets<DefFile<T::Asset>>>>,
+    mut events: EventWriter<DefFolderReady<T>>,
+) {
+    for folder in folders.folders.iter_mut().filter(|folder| !folder.ready) {
+        if folder.assets.is_none() && asset_server.is_loaded_with_dependencies(&folder.handle) {
+            let Some(loaded) = loaded_folders.get(&folder.handle) else {
+                continue;
+            };
+
+            let mut assets = Vec::new();
+            for handle in &loaded.handles {
+                if let Ok(handle) = handle.clone().try_typed::<T::Asset>() {
+                    assets.push(handle.id());
+                } else if let Ok(handle) = handle.clone().try_typed::<DefFile<T::Asset>>() {
+                    let file = files.as_ref().and_then(|files| files.get(&handle));
+                    assets.extend(file.into_iter().flat_map(|file| &file.defs).map(Handle::id));
+                }
+            }
+
+            folder.assets = Some(assets);
+        }
+
+        let Some(assets) = &folder.assets else {
+            continue;
+        };
+
+        folder.registered = assets.iter().filter(|&&id| index.is_indexed(id)).count();
+
+        if folder.registered == assets.len() {
+            info!("all {} defs in {} are ready", assets.len(), folder.path);
+            folder.ready = true;
+            events.write(DefFolderReady {
+                path: folder.path.clone(),
+                marker: PhantomData,
+            });
+        }
+    }
+}
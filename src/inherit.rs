@@ -0,0 +1,226 @@
+use crate::{DefComponent, DefError, DefFolder, DefFolders, DefIndex};
+use bevy_asset::{AssetId, Assets};
+use bevy_ecs::world::World;
+use bevy_log::{error, info, warn};
+use bevy_platform::collections::HashMap;
+use std::{any::type_name, borrow::Cow};
+
+/// Defs whose assets can inherit from a parent def, enabled with
+/// [`DefPlugin::with_inheritance`](crate::DefPlugin::with_inheritance).
+///
+/// A child is resolved against its chain of parents before it's registered,
+/// and the resolved asset becomes the asset of the def. Abstract parents are never registered.
+pub trait DefInherit: DefComponent {
+    /// The `defname` of the parent of this asset.
+    fn parent(asset: &Self::Asset) -> Option<&str>;
+
+    /// Whether this asset is only a parent for other defs.
+    fn is_abstract(asset: &Self::Asset) -> bool;
+
+    /// Fills the fields `child` doesn't set from its already resolved `parent`.
+    fn inherit(child: &Self::Asset, parent: &Self::Asset) -> Self::Asset;
+}
+
+pub(crate) struct InheritFns<T: DefComponent> {
+    parent: for<'a> fn(&'a T::Asset) -> Option<&'a str>,
+    is_abstract: fn(&T::Asset) -> bool,
+    inherit: fn(&T::Asset, &T::Asset) -> T::Asset,
+}
+
+impl<T: DefComponent> Clone for InheritFns<T> {
+    fn clone(&self) -> Self {
+        Self {
+            parent: self.parent,
+            is_abstract: self.is_abstract,
+            inherit: self.inherit,
+        }
+    }
+}
+
+impl<T: DefInherit> Default for InheritFns<T> {
+    fn default() -> Self {
+        Self {
+            parent: T::parent,
+            is_abstract: T::is_abstract,
+            inherit: T::inherit,
+        }
+    }
+}
+
+impl<T: DefComponent> DefIndex<T> {
+    /// Records `id` as a possible parent. Returns `true` if it's abstract
+    /// or has a parent, so it must not be registered as it is.
+    pub(crate) fn add_template(&mut self, world: &World, id: AssetId<T::Asset>) -> bool {
+        let Some(inheritance) = &self.inheritance else {
+            return false;
+        };
+        if self.is_resolved(id) {
+            return false;
+        }
+        let Some(asset) = world.resource::<Assets<T::Asset>>().get(id) else {
+            return false;
+        };
+
+        self.templates.insert(id);
+
+        if (inheritance.is_abstract)(asset) {
+            info!("abstract def {} ({id})", T::defname(asset));
+            return true;
+        }
+
+        if (inheritance.parent)(asset).is_some() {
+            self.unresolved.insert(id);
+            return true;
+        }
+
+        false
+    }
+
+    pub(crate) fn is_template(&self, id: AssetId<T::Asset>) -> bool {
+        self.templates.contains(&id)
+    }
+
+    pub(crate) fn is_resolved(&self, id: AssetId<T::Asset>) -> bool {
+        self.resolved.values().any(|handle| handle.id() == id)
+    }
+
+    /// Registers the children whose parents are all there now.
+    pub(crate) fn resolve_waiting(&mut self, world: &mut World) {
+        let waiting: Vec<_> = self.unresolved.iter().copied().collect();
+
+        for id in waiting {
+            // Registering a child resolves its own children, which can include
+            // later entries of `waiting`.
+            if !self.unresolved.contains(&id) {
+                continue;
+            }
+
+            let asset = match self.resolve(world, id) {
+                Ok(Some(asset)) => asset,
+                Ok(None) => continue,
+                Err(err @ DefError::MissingParent { .. }) => {
+                    self.missing_parents.insert(id, err);
+                    continue;
+                }
+                Err(err) => {
+                    error!("{err}");
+                    self.unresolved.remove(&id);
+                    self.missing_parents.remove(&id);
+                    continue;
+                }
+            };
+
+            self.unresolved.remove(&id);
+            self.missing_parents.remove(&id);
+            self.reported_parents.remove(&id);
+
+            let handle = world.resource_mut::<Assets<T::Asset>>().add(asset);
+            let resolved = handle.id();
+            self.resolved.insert(id, handle);
+            if let Some(event) = self.register(world, resolved) {
+                world.send_event(event);
+            }
+
+            if let Some(&component_id) = self.asset_to_id.get(&resolved) {
+                self.asset_to_id.insert(id, component_id);
+            }
+        }
+    }
+
+    /// Resolves the registered children again, after one of their parents changed.
+    pub(crate) fn reresolve(&mut self, world: &mut World) {
+        let resolved: Vec<_> = self
+            .resolved
+            .iter()
+            .map(|(&id, handle)| (id, handle.clone()))
+            .collect();
+
+        for (id, handle) in resolved {
+            match self.resolve(world, id) {
+                Ok(Some(asset)) => world
+                    .resource_mut::<Assets<T::Asset>>()
+                    .insert(&handle, asset),
+                Ok(None) => {}
+                Err(err) => error!("{err}"),
+            }
+        }
+    }
+
+    /// Reports children whose parent is still missing once the folders of `T` are loaded
+    /// and every [requested](DefIndex::request) def arrived, so no other parent is on its way.
+    pub(crate) fn report_missing_parents(&mut self, world: &World) {
+        if self.missing_parents.is_empty() || !self.pending.is_empty() {
+            return;
+        }
+
+        let folders_loaded = world
+            .get_resource::<DefFolders<T>>()
+            .is_none_or(|folders| folders.iter().all(DefFolder::is_loaded));
+        let requested_loaded = self.requested.iter().all(|id| self.indexed.contains(id));
+        if !folders_loaded || !requested_loaded {
+            return;
+        }
+
+        for (&id, err) in &self.missing_parents {
+            if self.reported_parents.insert(id) {
+                warn!("{err}");
+            }
+        }
+    }
+
+    /// Builds the asset of `id` from its chain of parents, `None` if it isn't there.
+    fn resolve(&self, world: &World, id: AssetId<T::Asset>) -> Result<Option<T::Asset>, DefError> {
+        let Some(inheritance) = &self.inheritance else {
+            return Ok(None);
+        };
+
+        let assets = world.resource::<Assets<T::Asset>>();
+        let templates: HashMap<_, _> = self
+            .templates
+            .iter()
+            .filter_map(|&id| Some((T::defname(assets.get(id)?), id)))
+            .collect();
+
+        let Some(mut current) = assets.get(id) else {
+            return Ok(None);
+        };
+
+        let mut chain = vec![current];
+        let mut names = vec![T::defname(current)];
+
+        while let Some(parent) = (inheritance.parent)(current) {
+            if names.iter().any(|name| name == parent) {
+                names.push(Cow::Owned(parent.to_string()));
+                return Err(DefError::InheritanceCycle {
+                    ty: type_name::<T>(),
+                    chain: names,
+                });
+            }
+
+            let Some(parent_asset) = templates.get(parent).and_then(|&id| assets.get(id)) else {
+                names.push(Cow::Owned(parent.to_string()));
+                return Err(DefError::MissingParent {
+                    ty: type_name::<T>(),
+                    chain: names,
+                });
+            };
+
+            names.push(T::defname(parent_asset));
+            chain.push(parent_asset);
+            current = parent_asset;
+        }
+
+        let mut chain = chain.into_iter().rev();
+        let Some(root) = chain.next() else {
+            return Ok(None);
+        };
+
+        let mut resolved = None;
+        for child in chain {
+            let parent = resolved.as_ref().unwrap_or(root);
+            resolved = Some((inheritance.inherit)(child, parent));
+        }
+
+        Ok(resolved)
+    }
+}
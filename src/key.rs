@@ -0,0 +1,48 @@
+use crate::DefComponent;
+use bevy_reflect::Reflect;
+use std::fmt;
+
+/// Identifier of a def that is the same on every run and machine, unlike
+/// [`ComponentId`](bevy_ecs::component::ComponentId)s and [`AssetId`](bevy_asset::AssetId)s
+/// that follow the order assets arrive in.
+///
+/// It's a 64-bit FNV-1a hash of [`DefComponent::key_namespace`] and the `defname`,
+/// so renaming a def changes its key.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Reflect)]
+pub struct DefKey(u64);
+
+impl DefKey {
+    pub fn new<T: DefComponent>(defname: &str) -> Self {
+        Self::from_parts(T::key_namespace(), defname)
+    }
+
+    pub fn from_parts(namespace: &str, defname: &str) -> Self {
+        const OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+        const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+        let bytes = namespace.bytes().chain([0]).chain(defname.bytes());
+        Self(bytes.fold(OFFSET, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(PRIME)
+        }))
+    }
+
+    pub const fn from_bits(bits: u64) -> Self {
+        Self(bits)
+    }
+
+    pub const fn to_bits(self) -> u64 {
+        self.0
+    }
+}
+
+impl fmt::Debug for DefKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DefKey({:016x})", self.0)
+    }
+}
+
+impl fmt::Display for DefKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
@@ -1,40 +1,154 @@
 use bevy_app::{App, Plugin, PostUpdate};
-use bevy_asset::{Asset, AssetApp, AssetEvent, AssetEvents, AssetId, Assets};
+use bevy_asset::{Asset, AssetApp, AssetEvent, AssetEvents, AssetId, AssetServer, Assets, Handle};
 use bevy_ecs::{
-    component::{
-        ComponentCloneBehavior, ComponentDescriptor, ComponentHook, ComponentId, StorageType,
-    },
-    event::{EventCursor, Events},
+    change_detection::DetectChangesMut,
+    component::{ComponentDescriptor, ComponentHook, ComponentId, Components, StorageType},
+    entity::EntityMapper,
+    event::{Event, EventCursor, Events},
     query::Access,
     resource::Resource,
     schedule::IntoScheduleConfigs,
-    system::{EntityCommand, Local, Res, SystemParam},
-    world::{EntityWorldMut, FilteredEntityMut, FilteredEntityRef, Mut, World},
+    system::{Local, Res, SystemParam},
+    world::{FilteredEntityMut, FilteredEntityRef, Mut, World},
 };
 use bevy_log::{error, info, warn};
-use bevy_platform::collections::HashMap;
+use bevy_platform::collections::{HashMap, HashSet};
 use bevy_ptr::OwningPtr;
+use bevy_state::state::FreelyMutableState;
+use serde::de::DeserializeOwned;
 use std::{
     alloc::Layout,
+    any::{TypeId, type_name},
     borrow::{Borrow, Cow},
     hash::Hash,
     marker::PhantomData,
-    mem::needs_drop,
+    mem::{self, needs_drop},
 };
 
+mod clone;
+mod commands;
 mod debug;
 mod entity_mut;
 mod entity_ref;
+mod error;
+mod filter;
+mod folder;
+mod inherit;
+mod key;
+mod link;
+mod loader;
+mod patch;
+mod query;
+mod reflect;
+mod scene;
+mod state;
 
+pub use self::clone::DefCloneBehavior;
+pub use self::commands::{InsertDef, RemoveDef};
+use self::commands::{QueuedDef, insert_by_id};
 pub use self::entity_mut::DefEntityMut;
 pub use self::entity_ref::DefEntityRef;
+pub use self::error::DefError;
+pub use self::filter::{
+    DefAdded, DefChanged, DefFilterFetch, DefFilterState, DefPicked, DefSelector, DefWith,
+    DefWithout,
+};
+pub use self::folder::{DefFolder, DefFolderReady, DefFolders, def_folder_system};
+pub use self::inherit::DefInherit;
+use self::inherit::InheritFns;
+pub use self::key::DefKey;
+pub use self::link::{DefLink, DefLinks, def_link_system};
+pub use self::loader::{DefFile, DefFileLoader, DefLoader, DefLoaderError};
+pub use self::patch::DefPatch;
+use self::patch::{Merged, Patch, PatchFns};
+pub use self::query::{DefQuery, DefQueryMut, DefQueryParamState};
+pub use self::reflect::{DefComponentTypes, ReflectDefComponent};
+pub use self::scene::{DefScenePlugin, SceneDefs, extract_scene_defs};
+use self::state::AddLoadingState;
+pub use self::state::DefLoadingState;
+pub use bevy_def_macros::DefComponent;
+
+#[doc(hidden)]
+pub mod __macro_exports {
+    pub use bevy_asset::Asset;
+    pub use bevy_ecs::component::{ComponentHook, StorageType};
+    pub use bevy_ecs::entity::EntityMapper;
+}
+
+/// Components reserved for defs at a time, see [`DefPlugin::with_capacity`].
+pub const DEFAULT_CAPACITY: usize = 256;
 
 pub struct DefQueryState {
     index_id: ComponentId,
     asset_id: ComponentId,
+    access: Access<ComponentId>,
+    /// How many reserved components of the index the access covers, see [`DefIndex::is_claimed`].
+    claimed: usize,
 }
 
-pub unsafe trait DefComponent: Send + Sync + 'static {
+impl DefQueryState {
+    fn init<T: DefComponent>(world: &mut World, write: bool) -> Self {
+        let asset_id = world.init_resource::<Assets<T::Asset>>();
+        let index_id = world.init_resource::<DefIndex<T>>();
+
+        // Defs are registered long after most systems are built, so the access covers
+        // the components the index reserved for them instead of the registered ones.
+        DefIndex::<T>::init_reserved(world);
+
+        let mut index = world.resource_mut::<DefIndex<T>>();
+        index.bypass_change_detection().reserved_claimed = true;
+        let mut access = if write {
+            index.access_mut.clone()
+        } else {
+            index.access_ref.clone()
+        };
+        access.add_resource_read(index_id);
+        access.add_resource_read(asset_id);
+
+        Self {
+            index_id,
+            asset_id,
+            access,
+            claimed: index.reserved.len(),
+        }
+    }
+
+    fn get<T: DefComponent>(components: &Components, write: bool) -> Option<Self> {
+        let asset_id = components.resource_id::<Assets<T::Asset>>()?;
+        let index_id = components.resource_id::<DefIndex<T>>()?;
+
+        // The index is not reachable from here, so claim every component.
+        let mut access = Access::default();
+        if write {
+            access.write_all_components();
+        } else {
+            access.read_all_components();
+        }
+        access.add_resource_read(index_id);
+        access.add_resource_read(asset_id);
+
+        Some(Self {
+            index_id,
+            asset_id,
+            access,
+            claimed: usize::MAX,
+        })
+    }
+
+    pub fn access(&self) -> &Access<ComponentId> {
+        &self.access
+    }
+}
+
+/// A component type that is stored under a separate runtime component for every def asset.
+///
+/// Implementing this trait is safe. [`DefIndex`] registers every def component with the
+/// layout and drop function of `Self`, and the crate only reads a component back as `Self`
+/// after checking that the index of `Self` registered it, see [`DefIndex::contains_component`].
+///
+/// Implementations are expected to return the same [`StorageType`] for the whole run,
+/// and [`DefComponent::defname`] should only depend on the asset.
+pub trait DefComponent: Send + Sync + 'static {
     /// Asset attached to a component.
     type Asset: Asset;
 
@@ -44,6 +158,15 @@ pub unsafe trait DefComponent: Send + Sync + 'static {
     /// Gets the name of the [`Component`] from the asset.
     fn defname(asset: &Self::Asset) -> Cow<'static, str>;
 
+    /// Hashed together with the `defname` into a [`DefKey`].
+    ///
+    /// `#[derive(DefComponent)]` uses the name of the struct, or `#[def(key = "...")]`.
+    /// Otherwise it defaults to [`type_name`], which can change with the module path
+    /// of the type or the compiler version, so manual impls should override it.
+    fn key_namespace() -> &'static str {
+        type_name::<Self>()
+    }
+
     /// Gets the `on_add` [`ComponentHook`] for this [`DefComponent`] if one is defined.
     fn on_add() -> Option<ComponentHook> {
         None
@@ -69,7 +192,24 @@ pub unsafe trait DefComponent: Send + Sync + 'static {
         None
     }
 
-    // TODO: map_entities, maybe ComponentCloneBehavior
+    /// Maps the entities this value refers to, see [`Component::map_entities`](bevy_ecs::component::Component::map_entities).
+    ///
+    /// Called when entities with the def are cloned, and when [`SceneDefs`] are spawned.
+    fn map_entities<E: EntityMapper>(_this: &mut Self, _mapper: &mut E) {}
+
+    /// How entity cloning copies def components of this type.
+    fn clone_behavior() -> DefCloneBehavior<Self>
+    where
+        Self: Sized,
+    {
+        DefCloneBehavior::Ignore
+    }
+}
+
+/// Builds the initial value of a def component from its asset,
+/// so defaults live in data and not in spawn code.
+pub trait DefDefault: DefComponent {
+    fn from_asset(asset: &Self::Asset) -> Self;
 }
 
 pub struct DefRef<'value, 'asset, T: DefComponent> {
@@ -82,19 +222,200 @@ pub struct DefMut<'value, 'asset, T: DefComponent> {
     pub asset: &'asset T::Asset,
 }
 
-pub struct DefPlugin<T: DefComponent>(PhantomData<fn() -> T>);
+/// What happens to entities when the asset of a def is removed or no longer used.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DefRemovalPolicy {
+    /// Keep the component on entities, the def is only marked as orphaned in [`DefIndex`].
+    #[default]
+    Orphan,
+    /// Remove the component from every entity that has it.
+    RemoveComponents,
+    /// Treat the removal as a bug.
+    Panic,
+}
+
+/// What happens when an asset returns the `defname` of another registered def.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DefCollisionPolicy {
+    /// Log an error and leave the new asset unregistered.
+    #[default]
+    Error,
+    /// The new asset replaces the old one as the asset of the def, and keeps its component.
+    Override,
+    /// Both assets point at the same component, the old one stays the asset of the def.
+    Alias,
+}
+
+pub struct DefPlugin<T: DefComponent> {
+    removal_policy: DefRemovalPolicy,
+    collision_policy: DefCollisionPolicy,
+    wait_for_dependencies: bool,
+    patching: Option<PatchFns<T>>,
+    inheritance: Option<InheritFns<T>>,
+    loader: Option<DefLoader<T::Asset>>,
+    file_loader: Option<DefFileLoader<T>>,
+    folders: Vec<String>,
+    loading_state: Option<AddLoadingState>,
+    links: Vec<fn(&mut App)>,
+    capacity: usize,
+    marker: PhantomData<fn() -> T>,
+}
 
 impl<T: DefComponent> Default for DefPlugin<T> {
     fn default() -> Self {
-        Self(PhantomData)
+        Self {
+            removal_policy: DefRemovalPolicy::default(),
+            collision_policy: DefCollisionPolicy::default(),
+            wait_for_dependencies: false,
+            patching: None,
+            inheritance: None,
+            loader: None,
+            file_loader: None,
+            folders: Vec::new(),
+            loading_state: None,
+            links: Vec::new(),
+            capacity: DEFAULT_CAPACITY,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DefComponent> DefPlugin<T> {
+    pub fn with_removal_policy(mut self, policy: DefRemovalPolicy) -> Self {
+        self.removal_policy = policy;
+        self
+    }
+
+    pub fn with_collision_policy(mut self, policy: DefCollisionPolicy) -> Self {
+        self.collision_policy = policy;
+        self
+    }
+
+    /// Number of components reserved for defs at a time, see [`DefIndex::capacity`].
+    pub fn with_capacity(mut self, capacity: usize) -> Self {
+        self.capacity = capacity.max(1);
+        self
+    }
+
+    /// Merges patch assets into the def they name instead of treating them as collisions,
+    /// see [`DefPatch`].
+    pub fn with_patches(mut self) -> Self
+    where
+        T: DefPatch,
+    {
+        self.patching = Some(PatchFns::default());
+        self
+    }
+
+    /// Resolves def assets against the parent they name before registering them,
+    /// see [`DefInherit`].
+    pub fn with_inheritance(mut self) -> Self
+    where
+        T: DefInherit,
+    {
+        self.inheritance = Some(InheritFns::default());
+        self
+    }
+
+    /// Loads def assets from files with these extensions, see [`DefLoader`].
+    pub fn with_loader(
+        mut self,
+        ron: &'static [&'static str],
+        json: &'static [&'static str],
+    ) -> Self
+    where
+        T::Asset: DeserializeOwned,
+    {
+        self.loader = Some(DefLoader::new(ron, json));
+        self
+    }
+
+    /// Loads files that declare many def assets, with these extensions, see [`DefFileLoader`].
+    pub fn with_file_loader(
+        mut self,
+        ron: &'static [&'static str],
+        json: &'static [&'static str],
+    ) -> Self
+    where
+        T::Asset: DeserializeOwned,
+    {
+        self.file_loader = Some(DefFileLoader::new(ron, json));
+        self
+    }
+
+    /// Loads every def asset in the folder `path` at startup, see [`DefFolders`].
+    pub fn with_folder(mut self, path: impl Into<String>) -> Self {
+        self.folders.push(path.into());
+        self
+    }
+
+    /// Keeps the app in the `loading` state until the defs of this type are registered,
+    /// then moves it to `next`, see [`DefLoadingState`].
+    pub fn with_loading_state<S: FreelyMutableState>(mut self, loading: S, next: S) -> Self {
+        self.loading_state = Some(Box::new(move |app| {
+            state::add_loading_state::<T, S>(app, loading.clone(), next.clone());
+        }));
+        self
+    }
+
+    /// Reports links of these defs to defs of type `U` that don't resolve, see [`DefLinks`].
+    /// `DefPlugin<U>` has to be added as well.
+    pub fn with_links<U: DefComponent>(mut self) -> Self
+    where
+        T: DefLinks<U>,
+    {
+        self.links.push(|app| {
+            app.add_systems(
+                PostUpdate,
+                def_link_system::<T, U>
+                    .after(def_folder_system::<T>)
+                    .after(def_folder_system::<U>),
+            );
+        });
+        self
+    }
+
+    /// Delays registration of loaded defs until [`AssetEvent::LoadedWithDependencies`].
+    /// Assets that are not managed by the [`AssetServer`] are registered right away.
+    pub fn with_wait_for_dependencies(mut self, wait: bool) -> Self {
+        self.wait_for_dependencies = wait;
+        self
     }
 }
 
 impl<T: DefComponent> Plugin for DefPlugin<T> {
     fn build(&self, app: &mut App) {
         app.init_resource::<DefIndex<T>>();
+        app.init_resource::<DefComponentTypes>();
         app.init_asset::<T::Asset>();
-        app.add_systems(PostUpdate, def_maintain_system::<T>.after(AssetEvents));
+        if let Some(loader) = &self.loader {
+            loader.register(app);
+        }
+        if let Some(loader) = &self.file_loader {
+            loader.register(app);
+        }
+        app.add_event::<DefEvent<T>>();
+        app.add_event::<DefFolderReady<T>>();
+        app.add_systems(
+            PostUpdate,
+            (def_maintain_system::<T>, def_folder_system::<T>)
+                .chain()
+                .after(AssetEvents),
+        );
+
+        let asset_server = app.world().resource::<AssetServer>().clone();
+        let mut folders = DefFolders::<T>::default();
+        for path in &self.folders {
+            folders.load(&asset_server, path.clone());
+        }
+        app.insert_resource(folders);
+
+        if let Some(add_loading_state) = &self.loading_state {
+            add_loading_state(app);
+        }
+        for add_links in &self.links {
+            add_links(app);
+        }
 
         let world = app.world_mut();
         let index_id = world.resource_id::<DefIndex<T>>().unwrap();
@@ -102,11 +423,20 @@ impl<T: DefComponent> Plugin for DefPlugin<T> {
 
         let mut index = world.resource_mut::<DefIndex<T>>();
 
+        index.removal_policy = self.removal_policy;
+        index.collision_policy = self.collision_policy;
+        index.patching = self.patching.clone();
+        index.inheritance = self.inheritance.clone();
+        index.wait_for_dependencies = self.wait_for_dependencies;
+        index.capacity = self.capacity;
+
         index.access_ref.add_resource_read(index_id);
         index.access_ref.add_resource_read(asset_id);
 
         index.access_mut.add_resource_read(index_id);
         index.access_mut.add_resource_read(asset_id);
+
+        DefIndex::<T>::init_reserved(world);
     }
 }
 
@@ -120,72 +450,118 @@ pub fn def_maintain_system<T: DefComponent>(
                 match event {
                     &AssetEvent::Added { id } => {
                         info!("added {id}");
-                        def_index.register(world, id);
+                        if let Some(event) = def_index.added(world, id) {
+                            world.send_event(event);
+                        }
+                    }
+                    &AssetEvent::Modified { id } => {
+                        info!("modified {id}");
+                        if let Some(event) = def_index.modified(world, id) {
+                            world.send_event(event);
+                        }
+                    }
+                    &AssetEvent::Removed { id } | &AssetEvent::Unused { id } => {
+                        info!("removed {id}");
+                        if let Some(event) = def_index.unregister(world, id) {
+                            world.send_event(event);
+                        }
                     }
-                    AssetEvent::Modified { id } => info!("modified {id}"),
-                    AssetEvent::Removed { id } => error!("removed {id}"),
-                    AssetEvent::Unused { id } => error!("unused {id}"),
-                    AssetEvent::LoadedWithDependencies { id } => {
-                        warn!("full loaded {id}")
+                    &AssetEvent::LoadedWithDependencies { id } => {
+                        info!("full loaded {id}");
+                        if !def_index.pending.remove(&id) {
+                            continue;
+                        }
+                        if let Some(event) = def_index.register(world, id) {
+                            world.send_event(event);
+                        }
                     }
                 }
             }
-        });
-    });
-}
 
-pub struct InsertDef<T: DefComponent> {
-    component_id: ComponentId,
-    value: T,
-}
-
-impl<T: DefComponent> InsertDef<T> {
-    pub const fn new(component_id: ComponentId, value: T) -> Self {
-        Self {
-            component_id,
-            value,
-        }
-    }
-}
-
-impl<T: DefComponent> EntityCommand for InsertDef<T> {
-    fn apply(self, mut entity: EntityWorldMut<'_>) {
-        OwningPtr::make(self.value, |component| unsafe {
-            entity.insert_by_id(self.component_id, component);
+            def_index.apply_queued(world);
+            def_index.report_missing_parents(world);
         });
-    }
-}
-
-pub struct RemoveDef<T: DefComponent> {
-    component_id: ComponentId,
-    marker: PhantomData<T>,
-}
-
-impl<T: DefComponent> RemoveDef<T> {
-    pub const fn new(component_id: ComponentId) -> Self {
-        Self {
-            component_id,
-            marker: PhantomData,
-        }
-    }
+    });
 }
 
-impl<T: DefComponent> EntityCommand for RemoveDef<T> {
-    fn apply(self, mut entity: EntityWorldMut<'_>) {
-        entity.remove_by_id(self.component_id);
-    }
+/// Notifications about changes in a [`DefIndex`].
+#[derive(Event)]
+pub enum DefEvent<T: DefComponent> {
+    /// The `defname` of a registered def changed, usually on hot reload.
+    /// The component keeps its [`ComponentId`], only the name lookup is updated.
+    Renamed {
+        id: AssetId<T::Asset>,
+        component_id: ComponentId,
+        old: Cow<'static, str>,
+        new: Cow<'static, str>,
+    },
+    /// A second asset returned the name of a registered def,
+    /// what happened to it depends on the [`DefCollisionPolicy`].
+    Collision {
+        name: Cow<'static, str>,
+        /// The asset that had the name first.
+        existing: AssetId<T::Asset>,
+        /// The asset that collided with it.
+        id: AssetId<T::Asset>,
+        component_id: ComponentId,
+        policy: DefCollisionPolicy,
+    },
+    /// The asset of a def was removed or is no longer used.
+    /// What happened to entities depends on the [`DefRemovalPolicy`].
+    Removed {
+        id: AssetId<T::Asset>,
+        component_id: ComponentId,
+        name: Cow<'static, str>,
+    },
 }
 
 #[derive(Resource)]
 pub struct DefIndex<T: DefComponent> {
     names: HashMap<Cow<'static, str>, (AssetId<T::Asset>, ComponentId)>,
+    /// Changes whenever a name moves to another def, so [`DefLink`]s know to resolve again.
+    generation: u64,
 
     asset_to_id: HashMap<AssetId<T::Asset>, ComponentId>,
     id_to_asset: HashMap<ComponentId, AssetId<T::Asset>>,
 
+    keys: HashMap<DefKey, (AssetId<T::Asset>, ComponentId)>,
+    id_to_key: HashMap<ComponentId, DefKey>,
+
+    orphans: HashMap<ComponentId, Cow<'static, str>>,
+    removal_policy: DefRemovalPolicy,
+    collision_policy: DefCollisionPolicy,
+
+    pending: HashSet<AssetId<T::Asset>>,
+    wait_for_dependencies: bool,
+    indexed: HashSet<AssetId<T::Asset>>,
+    requested: HashSet<AssetId<T::Asset>>,
+    /// Defs picked at runtime for [`DefPicked`] selectors, by marker type.
+    picked: HashMap<TypeId, ComponentId>,
+
+    queued: Vec<QueuedDef<T>>,
+
+    patching: Option<PatchFns<T>>,
+    patches: Vec<Patch<T>>,
+    patch_order: u64,
+    merged: HashMap<ComponentId, Merged<T>>,
+
+    inheritance: Option<InheritFns<T>>,
+    templates: HashSet<AssetId<T::Asset>>,
+    unresolved: HashSet<AssetId<T::Asset>>,
+    missing_parents: HashMap<AssetId<T::Asset>, DefError>,
+    reported_parents: HashSet<AssetId<T::Asset>>,
+    resolved: HashMap<AssetId<T::Asset>, Handle<T::Asset>>,
+
     access_ref: Access<ComponentId>,
     access_mut: Access<ComponentId>,
 
+    reserved: Vec<ComponentId>,
+    reserved_at: HashMap<ComponentId, usize>,
+    assigned: usize,
+    capacity: usize,
+    /// Whether a query claimed the reserved components, see [`DefIndex::capacity`].
+    reserved_claimed: bool,
+
     marker: PhantomData<fn() -> T>,
 }
 
@@ -193,34 +569,245 @@ impl<T: DefComponent> Default for DefIndex<T> {
     fn default() -> Self {
         Self {
             names: HashMap::default(),
+            generation: 0,
 
             asset_to_id: HashMap::default(),
             id_to_asset: HashMap::default(),
 
+            keys: HashMap::default(),
+            id_to_key: HashMap::default(),
+
+            orphans: HashMap::default(),
+            removal_policy: DefRemovalPolicy::default(),
+            collision_policy: DefCollisionPolicy::default(),
+
+            pending: HashSet::default(),
+            wait_for_dependencies: false,
+            indexed: HashSet::default(),
+            requested: HashSet::default(),
+            picked: HashMap::default(),
+
+            queued: Vec::new(),
+
+            patching: None,
+            patches: Vec::new(),
+            patch_order: 0,
+            merged: HashMap::default(),
+
+            inheritance: None,
+            templates: HashSet::default(),
+            unresolved: HashSet::default(),
+            missing_parents: HashMap::default(),
+            reported_parents: HashSet::default(),
+            resolved: HashMap::default(),
+
             access_ref: Access::default(),
             access_mut: Access::default(),
 
+            reserved: Vec::new(),
+            reserved_at: HashMap::default(),
+            assigned: 0,
+            capacity: DEFAULT_CAPACITY,
+            reserved_claimed: false,
+
             marker: PhantomData,
         }
     }
 }
 
 impl<T: DefComponent> DefIndex<T> {
-    fn register(&mut self, world: &mut World, id: impl Into<AssetId<T::Asset>>) {
+    fn added(&mut self, world: &mut World, id: AssetId<T::Asset>) -> Option<DefEvent<T>> {
+        if self.is_merged(id) || self.is_resolved(id) {
+            return None;
+        }
+
+        let waiting = self.wait_for_dependencies
+            && world.get_resource::<AssetServer>().is_some_and(|server| {
+                server.get_load_state(id).is_some() && !server.is_loaded_with_dependencies(id)
+            });
+
+        if waiting {
+            info!("waiting for dependencies of {id}");
+            self.pending.insert(id);
+            None
+        } else {
+            self.register(world, id)
+        }
+    }
+
+    fn register(
+        &mut self,
+        world: &mut World,
+        id: impl Into<AssetId<T::Asset>>,
+    ) -> Option<DefEvent<T>> {
         let id = id.into();
-        let name = T::defname(world.resource_mut::<Assets<T::Asset>>().get(id).unwrap());
-        self.names.entry(name.clone()).or_insert_with(|| {
+        self.indexed.insert(id);
+
+        if self.add_patch(world, id) {
+            return None;
+        }
+        if self.add_template(world, id) {
+            self.resolve_waiting(world);
+            return None;
+        }
+
+        let name = T::defname(world.resource::<Assets<T::Asset>>().get(id)?);
+
+        if let Some(&(existing, component_id)) = self.names.get(&name) {
+            return (existing != id).then(|| self.collide(name, existing, id, component_id));
+        }
+
+        // A def that comes back under the same name keeps its old component.
+        let orphan = self
+            .orphans
+            .iter()
+            .find_map(|(&component_id, orphan)| (*orphan == name).then_some(component_id));
+
+        let component_id = match orphan {
+            Some(component_id) => {
+                self.orphans.remove(&component_id);
+                component_id
+            }
+            None => self.register_component(world),
+        };
+
+        self.link(name.clone(), id, component_id);
+        self.rebuild(world, &name);
+        self.resolve_waiting(world);
+        None
+    }
+
+    fn modified(&mut self, world: &mut World, id: AssetId<T::Asset>) -> Option<DefEvent<T>> {
+        if self.is_merged(id) {
+            return None;
+        }
+
+        // A patch can move to another layer or def, or stop being a patch.
+        if self.remove_patch(world, id) {
+            return self.register(world, id);
+        }
+
+        if self.is_template(id) {
+            self.resolve_waiting(world);
+            self.reresolve(world);
+
+            // Abstract parents and children aren't the asset of a def themselves.
+            if self.resolved.contains_key(&id)
+                || self.unresolved.contains(&id)
+                || !self.asset_to_id.contains_key(&id)
+            {
+                return None;
+            }
+        }
+
+        if let Some(component_id) = self.merged_base(id) {
+            self.unmerge(component_id);
+        }
+
+        let event = self.rename(world, id);
+        if let Some(name) = self.name(id).cloned() {
+            self.rebuild(world, &name);
+        }
+        event
+    }
+
+    /// Handles a second asset with the name of a registered def, see [`DefCollisionPolicy`].
+    fn collide(
+        &mut self,
+        name: Cow<'static, str>,
+        existing: AssetId<T::Asset>,
+        id: AssetId<T::Asset>,
+        component_id: ComponentId,
+    ) -> DefEvent<T> {
+        match self.collision_policy {
+            DefCollisionPolicy::Error => {
+                error!("def {name} of {id} collides with {existing}, {id} isn't registered");
+            }
+            DefCollisionPolicy::Override => {
+                warn!("def {name} of {id} overrides {existing}");
+                self.asset_to_id.remove(&existing);
+                self.link(name.clone(), id, component_id);
+            }
+            DefCollisionPolicy::Alias => {
+                info!("def {name} of {id} is an alias of {existing}");
+                self.asset_to_id.insert(id, component_id);
+            }
+        }
+
+        DefEvent::Collision {
+            name,
+            existing,
+            id,
+            component_id,
+            policy: self.collision_policy,
+        }
+    }
+
+    /// Makes `id` the asset of the def `name`.
+    fn link(&mut self, name: Cow<'static, str>, id: AssetId<T::Asset>, component_id: ComponentId) {
+        self.insert_key(&name, id, component_id);
+        self.names.insert(name, (id, component_id));
+        self.generation += 1;
+        self.asset_to_id.insert(id, component_id);
+        self.id_to_asset.insert(component_id, id);
+    }
+
+    /// Gives the def the [`DefKey`] of `name`, unless another def hashed to it first.
+    fn insert_key(&mut self, name: &str, id: AssetId<T::Asset>, component_id: ComponentId) {
+        let key = DefKey::new::<T>(name);
+
+        if let Some(&(other, other_component_id)) = self.keys.get(&key)
+            && other_component_id != component_id
+        {
+            let other = self.name(other).cloned().unwrap_or_default();
+            error!("key {key} of def {name} ({id}) collides with def {other}, {name} has no key");
+            return;
+        }
+
+        self.keys.insert(key, (id, component_id));
+        self.id_to_key.insert(component_id, key);
+    }
+
+    fn remove_key(&mut self, component_id: ComponentId) {
+        let Some(key) = self.id_to_key.remove(&component_id) else {
+            return;
+        };
+        if self
+            .keys
+            .get(&key)
+            .is_some_and(|&(_, other_component_id)| other_component_id == component_id)
+        {
+            self.keys.remove(&key);
+        }
+    }
+
+    /// Reserves the first block of components if there is none yet.
+    pub(crate) fn init_reserved(world: &mut World) {
+        world.resource_scope(|world, mut index: Mut<Self>| {
+            if index.reserved.is_empty() {
+                index.reserve(world);
+            }
+        });
+    }
+
+    /// Registers [`DefIndex::capacity`] components for defs that aren't there yet,
+    /// so queries can claim access to them up front.
+    fn reserve(&mut self, world: &mut World) {
+        for _ in 0..self.capacity {
+            let position = self.reserved.len();
+            // The def a component goes to is only known later, and descriptors can't be renamed.
+            // Tools can look up the `defname` with `DefIndex::id_to_asset` or `ReflectDefComponent::defs`.
+            let name = format!("{}[{position}]", type_name::<T>());
+
+            // SAFETY: the layout and drop function both describe `T`.
             let component_id = world.register_component_with_descriptor(unsafe {
                 let layout = Layout::new::<T>();
                 let storage = T::STORAGE_TYPE;
                 let drop = needs_drop::<T>().then_some(Self::drop_ptr as _);
-                let clone = ComponentCloneBehavior::Default;
+                let clone = T::clone_behavior().component_clone_behavior();
                 ComponentDescriptor::new_with_layout(name, storage, layout, drop, true, clone)
             });
 
-            self.asset_to_id.insert(id, component_id);
-            self.id_to_asset.insert(component_id, id);
-
             self.access_ref.add_component_read(component_id);
             self.access_mut.add_component_write(component_id);
 
@@ -242,8 +829,186 @@ impl<T: DefComponent> DefIndex<T> {
                 hooks.on_despawn(hook);
             }
 
-            (id, component_id)
-        });
+            self.reserved.push(component_id);
+            self.reserved_at.insert(component_id, position);
+        }
+    }
+
+    /// Hands out the next reserved component to a new def.
+    fn register_component(&mut self, world: &mut World) -> ComponentId {
+        if self.assigned == self.reserved.len() {
+            // Queries only see the components reserved when they were built.
+            assert!(
+                !self.reserved_claimed,
+                "more than {} defs of `{}` after queries were built, see `DefPlugin::with_capacity`",
+                self.reserved.len(),
+                type_name::<T>()
+            );
+            self.reserve(world);
+        }
+
+        let component_id = self.reserved[self.assigned];
+        self.assigned += 1;
+
+        world
+            .get_resource_or_init::<DefComponentTypes>()
+            .insert::<T>(component_id);
+
+        component_id
+    }
+
+    fn rename(&mut self, world: &World, id: AssetId<T::Asset>) -> Option<DefEvent<T>> {
+        let component_id = self.asset_to_id.get(&id).copied()?;
+        let new = T::defname(world.resource::<Assets<T::Asset>>().get(id)?);
+
+        let old = self.name(id)?.clone();
+
+        if old == new {
+            return None;
+        }
+
+        if let Some(&(other, _)) = self.names.get(&new) {
+            warn!("can't rename {old} to {new} for {id}: the name is already used by {other}");
+            return None;
+        }
+
+        info!("renamed {old} to {new} for {id}");
+        self.names.remove(&old);
+        self.names.insert(new.clone(), (id, component_id));
+        self.generation += 1;
+
+        self.remove_key(component_id);
+        self.insert_key(&new, id, component_id);
+
+        Some(DefEvent::Renamed {
+            id,
+            component_id,
+            old,
+            new,
+        })
+    }
+
+    fn unregister(&mut self, world: &mut World, id: AssetId<T::Asset>) -> Option<DefEvent<T>> {
+        self.pending.remove(&id);
+        self.indexed.remove(&id);
+        self.requested.remove(&id);
+        self.templates.remove(&id);
+        self.unresolved.remove(&id);
+        self.missing_parents.remove(&id);
+        self.reported_parents.remove(&id);
+
+        if let Some(handle) = self.resolved.remove(&id) {
+            self.asset_to_id.remove(&id);
+            return self.unregister(world, handle.id());
+        }
+
+        if self.remove_patch(world, id) {
+            return None;
+        }
+
+        if let Some(component_id) = self.merged_base(id) {
+            self.unmerge(component_id);
+        }
+
+        let component_id = self.asset_to_id.remove(&id)?;
+
+        if self.id_to_asset.get(&component_id) != Some(&id) {
+            // An alias, the def stays with its own asset.
+            return None;
+        }
+
+        let alias = self
+            .asset_to_id
+            .iter()
+            .find_map(|(&alias, &other)| (other == component_id).then_some(alias));
+
+        if let Some(alias) = alias {
+            let name = self.name(id)?.clone();
+            info!("def {name} moves from removed {id} to its alias {alias}");
+            self.link(name, alias, component_id);
+            return None;
+        }
+
+        self.id_to_asset.remove(&component_id);
+
+        self.remove_key(component_id);
+
+        let name = self.name(id)?.clone();
+        self.names.remove(&name);
+        self.generation += 1;
+
+        match self.removal_policy {
+            DefRemovalPolicy::Orphan => warn!("def {name} ({id}) was removed"),
+            DefRemovalPolicy::RemoveComponents => {
+                let entities: Vec<_> = world
+                    .archetypes()
+                    .iter()
+                    .filter(|archetype| archetype.contains(component_id))
+                    .flat_map(|archetype| archetype.entities().iter().map(|entity| entity.id()))
+                    .collect();
+
+                warn!(
+                    "def {name} ({id}) was removed, removing it from {} entities",
+                    entities.len()
+                );
+
+                for entity in entities {
+                    world.entity_mut(entity).remove_by_id(component_id);
+                }
+            }
+            DefRemovalPolicy::Panic => panic!("def {name} ({id}) was removed"),
+        }
+
+        self.orphans.insert(component_id, name.clone());
+
+        Some(DefEvent::Removed {
+            id,
+            component_id,
+            name,
+        })
+    }
+
+    /// Inserts queued defs whose target got registered, in the order they were queued,
+    /// so the last insertion for an entity wins.
+    fn apply_queued(&mut self, world: &mut World) {
+        let mut ready = Vec::new();
+        let mut waiting = Vec::new();
+
+        for queued in mem::take(&mut self.queued) {
+            match queued.target.resolve_in(self) {
+                Some(component_id) => ready.push((component_id, queued)),
+                None => waiting.push(queued),
+            }
+        }
+        self.queued = waiting;
+
+        for (component_id, QueuedDef { entity, value, .. }) in ready {
+            let asset_id = self.id_to_asset.get(&component_id).copied();
+            let value = match value.make(world, component_id, asset_id) {
+                Ok(value) => value,
+                Err(err) => {
+                    warn!("dropping queued def for {entity}: {err}");
+                    continue;
+                }
+            };
+
+            match world.get_entity_mut(entity) {
+                Ok(mut entity) => insert_by_id(&mut entity, component_id, value),
+                Err(_) => info!("dropping queued def for despawned {entity}"),
+            }
+        }
+    }
+
+    /// Number of [`InsertDef::deferred`] insertions that still wait for their def.
+    pub fn queued_insertions(&self) -> usize {
+        self.queued.len()
+    }
+
+    pub fn name(&self, id: impl Into<AssetId<T::Asset>>) -> Option<&Cow<'static, str>> {
+        let id = id.into();
+        self.names
+            .iter()
+            .find_map(|(name, &(asset_id, _))| (asset_id == id).then_some(name))
     }
 
     pub fn find_by_name<Q>(&self, name: &Q) -> Option<(AssetId<T::Asset>, ComponentId)>
@@ -266,6 +1031,85 @@ impl<T: DefComponent> DefIndex<T> {
         &self.id_to_asset
     }
 
+    pub fn find_by_key(&self, key: DefKey) -> Option<(AssetId<T::Asset>, ComponentId)> {
+        self.keys.get(&key).copied()
+    }
+
+    pub fn key(&self, id: ComponentId) -> Option<DefKey> {
+        self.id_to_key.get(&id).copied()
+    }
+
+    pub fn asset_key(&self, id: impl Into<AssetId<T::Asset>>) -> Option<DefKey> {
+        self.key(*self.asset_to_id.get(&id.into())?)
+    }
+
+    pub fn keys(&self) -> &HashMap<DefKey, (AssetId<T::Asset>, ComponentId)> {
+        &self.keys
+    }
+
+    /// Defs that were added but still wait for their dependencies to load.
+    pub fn pending(&self) -> &HashSet<AssetId<T::Asset>> {
+        &self.pending
+    }
+
+    pub fn is_pending(&self, id: impl Into<AssetId<T::Asset>>) -> bool {
+        self.pending.contains(&id.into())
+    }
+
+    /// Whether the asset `id` went through registration: it's a def, a patch or a parent,
+    /// or it was rejected. Children still waiting for a parent aren't indexed yet.
+    pub fn is_indexed(&self, id: impl Into<AssetId<T::Asset>>) -> bool {
+        let id = id.into();
+        self.indexed.contains(&id) && !self.unresolved.contains(&id)
+    }
+
+    /// Waits for the def asset `id` in [`DefIndex::is_loaded`], for assets that
+    /// aren't loaded from a folder of [`DefFolders`].
+    pub fn request(&mut self, id: impl Into<AssetId<T::Asset>>) {
+        self.requested.insert(id.into());
+    }
+
+    pub fn requested(&self) -> &HashSet<AssetId<T::Asset>> {
+        &self.requested
+    }
+
+    /// Whether every [requested](DefIndex::request) def is indexed and
+    /// no def waits for its dependencies or its parent.
+    pub fn is_loaded(&self) -> bool {
+        self.pending.is_empty()
+            && self.unresolved.is_empty()
+            && self.requested.iter().all(|&id| self.indexed.contains(&id))
+    }
+
+    /// Components of defs whose asset was removed, with their last known name.
+    pub fn orphans(&self) -> &HashMap<ComponentId, Cow<'static, str>> {
+        &self.orphans
+    }
+
+    pub fn is_orphaned(&self, id: ComponentId) -> bool {
+        self.orphans.contains_key(&id)
+    }
+
+    /// Number of components reserved for defs at a time. Queries claim access to the
+    /// reserved components when they are built, so registering more defs than that
+    /// once a query was built panics.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Whether `id` is one of the first `claimed` components reserved by this index.
+    pub(crate) fn is_claimed(&self, id: ComponentId, claimed: usize) -> bool {
+        self.reserved_at
+            .get(&id)
+            .is_some_and(|&position| position < claimed)
+    }
+
+    /// Whether `id` was registered by this index, so its values are of type `T`.
+    /// Orphaned components are included.
+    pub fn contains_component(&self, id: ComponentId) -> bool {
+        self.id_to_asset.contains_key(&id) || self.orphans.contains_key(&id)
+    }
+
     pub fn access_ref(&self) -> Access<ComponentId> {
         self.access_ref.clone()
     }
@@ -275,7 +1119,7 @@ impl<T: DefComponent> DefIndex<T> {
     }
 
     unsafe fn drop_ptr(x: OwningPtr<'_>) {
-        // SAFETY: Contract is required to be upheld by the caller.
+        // SAFETY: only used as the drop function of components that store `T`.
         unsafe { x.drop_as::<T>() }
     }
 }
@@ -288,18 +1132,29 @@ pub struct DefParam<'w, T: DefComponent> {
 
 impl<'w, T: DefComponent> DefParam<'w, T> {
     pub fn asset(&self, id: impl Into<AssetId<T::Asset>>) -> Option<(ComponentId, &'_ T::Asset)> {
-        let asset_index = id.into();
-        let asset = self.asset.get(asset_index)?;
-        let component_id = self.index.asset_to_id.get(&asset_index).copied()?;
+        let component_id = self.index.asset_to_id.get(&id.into()).copied()?;
+        let asset = self
+            .asset
+            .get(*self.index.id_to_asset.get(&component_id)?)?;
         Some((component_id, asset))
     }
 
+    /// Resolves `link` to the def it names.
+    pub fn get(&self, link: &DefLink<T>) -> Result<(ComponentId, &'_ T::Asset), DefError> {
+        let (id, component_id) = link.resolve(&self.index)?;
+        self.asset(id).ok_or(DefError::MissingAsset {
+            ty: type_name::<T>(),
+            component_id,
+        })
+    }
+
     pub fn filtered_entity_ref<'a>(
         &self,
         entity: &'a FilteredEntityRef<'w>,
         id: impl Into<AssetId<T::Asset>>,
     ) -> Option<DefRef<'a, '_, T>> {
         let (component_id, asset) = self.asset(id)?;
+        // SAFETY: `component_id` was registered by the index, so it stores `T`.
         let value = unsafe { entity.get_by_id(component_id)?.deref() };
         Some(DefRef { value, asset })
     }
@@ -310,6 +1165,7 @@ impl<'w, T: DefComponent> DefParam<'w, T> {
         id: impl Into<AssetId<T::Asset>>,
     ) -> Option<DefMut<'a, '_, T>> {
         let (component_id, asset) = self.asset(id)?;
+        // SAFETY: `component_id` was registered by the index, so it stores `T`.
         let value = unsafe { entity.get_mut_by_id(component_id)?.with_type::<T>() };
         Some(DefMut { value, asset })
     }
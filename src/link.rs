@@ -0,0 +1,201 @@
+use crate::{DefComponent, DefError, DefFolders, DefIndex, DefParam};
+use bevy_asset::{AssetEvent, AssetId};
+use bevy_ecs::{
+    component::ComponentId,
+    event::EventReader,
+    system::{Local, Res},
+};
+use bevy_log::{error, info};
+use bevy_platform::collections::HashSet;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use std::{any::type_name, borrow::Cow, fmt, marker::PhantomData, sync::RwLock};
+
+/// A reference from a def asset to a def of type `U`, stored by `defname` in asset files.
+///
+/// It's resolved through [`DefIndex<U>`] the first time it's used after the def
+/// registers, and the result is kept until a name in the index moves, so it follows
+/// the def when its asset is replaced. See [`DefParam::get`](crate::DefParam::get).
+pub struct DefLink<U: DefComponent> {
+    name: Cow<'static, str>,
+    resolved: RwLock<Option<Resolved<U>>>,
+    marker: PhantomData<fn() -> U>,
+}
+
+/// A resolved link, valid while the generation of the [`DefIndex`] is the same.
+struct Resolved<U: DefComponent> {
+    generation: u64,
+    id: AssetId<U::Asset>,
+    component_id: ComponentId,
+}
+
+impl<U: DefComponent> Clone for Resolved<U> {
+    fn clone(&self) -> Self {
+        Self {
+            generation: self.generation,
+            id: self.id,
+            component_id: self.component_id,
+        }
+    }
+}
+
+impl<U: DefComponent> DefLink<U> {
+    pub fn new(name: impl Into<Cow<'static, str>>) -> Self {
+        Self {
+            name: name.into(),
+            resolved: RwLock::new(None),
+            marker: PhantomData,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn resolve(
+        &self,
+        index: &DefIndex<U>,
+    ) -> Result<(AssetId<U::Asset>, ComponentId), DefError> {
+        if let Some(resolved) = self.resolved()
+            && resolved.generation == index.generation
+        {
+            return Ok((resolved.id, resolved.component_id));
+        }
+
+        let (id, component_id) =
+            index
+                .find_by_name(self.name.as_ref())
+                .ok_or_else(|| DefError::UnknownName {
+                    ty: type_name::<U>(),
+                    name: self.name.clone(),
+                })?;
+
+        if let Ok(mut resolved) = self.resolved.write() {
+            *resolved = Some(Resolved {
+                generation: index.generation,
+                id,
+                component_id,
+            });
+        }
+        Ok((id, component_id))
+    }
+
+    fn resolved(&self) -> Option<Resolved<U>> {
+        self.resolved.read().ok()?.clone()
+    }
+}
+
+impl<U: DefComponent> Clone for DefLink<U> {
+    fn clone(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            resolved: RwLock::new(self.resolved()),
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<U: DefComponent> PartialEq for DefLink<U> {
+    fn eq(&self, other: &Self) -> bool {
+        self.name == other.name
+    }
+}
+
+impl<U: DefComponent> Eq for DefLink<U> {}
+
+impl<U: DefComponent> fmt::Debug for DefLink<U> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "DefLink<{}>({})", type_name::<U>(), self.name)
+    }
+}
+
+impl<U: DefComponent> Serialize for DefLink<U> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.name)
+    }
+}
+
+impl<'de, U: DefComponent> Deserialize<'de> for DefLink<U> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::new)
+    }
+}
+
+/// Defs whose assets link to defs of type `U`, checked with
+/// [`DefPlugin::with_links`](crate::DefPlugin::with_links).
+pub trait DefLinks<U: DefComponent>: DefComponent {
+    fn links(asset: &Self::Asset) -> Vec<&DefLink<U>>;
+}
+
+/// What [`def_link_system`] found on its last run.
+#[derive(Default)]
+pub struct LinkCheck {
+    checked: bool,
+    reported: HashSet<(ComponentId, Cow<'static, str>)>,
+}
+
+/// Reports the links of defs of type `T` to defs of type `U` that don't resolve,
+/// and resolves the others ahead of their first use.
+///
+/// Links are only checked once every def of `U` is known: its folders are ready and
+/// [`DefIndex::is_loaded`]. Without folders or [requested](DefIndex::request) defs of `U`
+/// there is no such point, and nothing is reported.
+pub fn def_link_system<T, U>(
+    defs: DefParam<T>,
+    folders: Res<DefFolders<T>>,
+    links: DefParam<U>,
+    link_folders: Res<DefFolders<U>>,
+    mut events: EventReader<AssetEvent<T::Asset>>,
+    mut link_events: EventReader<AssetEvent<U::Asset>>,
+    mut state: Local<LinkCheck>,
+) where
+    T: DefLinks<U>,
+    U: DefComponent,
+{
+    let expected = link_folders.iter().next().is_some() || !links.index.requested().is_empty();
+    let loaded = folders.is_ready()
+        && defs.index.is_loaded()
+        && expected
+        && link_folders.is_ready()
+        && links.index.is_loaded();
+
+    // Defs only change with their assets, and the first check is once both are loaded.
+    let changed = events.read().count() + link_events.read().count() > 0;
+    if !loaded || (state.checked && !changed) {
+        return;
+    }
+    state.checked = true;
+
+    let mut dangling = HashSet::new();
+
+    for (name, &(id, component_id)) in defs.index.names() {
+        let Some(asset) = defs.asset.get(id) else {
+            continue;
+        };
+
+        for link in T::links(asset) {
+            if links.get(link).is_ok() {
+                continue;
+            }
+
+            let key = (component_id, link.name.clone());
+            if !state.reported.contains(&key) {
+                error!(
+                    "{}",
+                    DefError::DanglingLink {
+                        from_ty: type_name::<T>(),
+                        from: name.clone(),
+                        ty: type_name::<U>(),
+                        name: link.name.clone(),
+                    }
+                );
+            }
+            dangling.insert(key);
+        }
+    }
+
+    for (component_id, name) in state.reported.difference(&dangling) {
+        info!("link of {component_id:?} to {name} resolves now");
+    }
+
+    state.reported = dangling;
+}
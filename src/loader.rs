@@ -0,0 +1,201 @@
+use crate::DefComponent;
+use bevy_app::App;
+use bevy_asset::{Asset, AssetApp, AssetLoader, Handle, LoadContext, io::Reader};
+use bevy_platform::collections::HashSet;
+use bevy_reflect::TypePath;
+use serde::de::DeserializeOwned;
+use std::marker::PhantomData;
+use thiserror::Error;
+
+/// Loads def assets from RON and JSON files, added with
+/// [`DefPlugin::with_loader`](crate::DefPlugin::with_loader).
+///
+/// The format is picked by the extension of the file, so `"stat.ron"` and `"stat.json"`
+/// can both load `StatAsset`. Loaded assets are registered like any other.
+pub struct DefLoader<A> {
+    json: &'static [&'static str],
+    extensions: Vec<&'static str>,
+    register: fn(&mut App, Self),
+    marker: PhantomData<fn() -> A>,
+}
+
+impl<A> DefLoader<A>
+where
+    A: Asset + DeserializeOwned,
+{
+    /// Extensions are given without the leading dot, and can have several parts like `"stat.ron"`.
+    pub fn new(ron: &'static [&'static str], json: &'static [&'static str]) -> Self {
+        Self {
+            json,
+            extensions: ron.iter().chain(json).copied().collect(),
+            register: |app, loader| {
+                app.register_asset_loader(loader);
+            },
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<A> DefLoader<A> {
+    pub(crate) fn register(&self, app: &mut App) {
+        (self.register)(app, self.clone());
+    }
+}
+
+impl<A> Clone for DefLoader<A> {
+    fn clone(&self) -> Self {
+        Self {
+            json: self.json,
+            extensions: self.extensions.clone(),
+            register: self.register,
+            marker: PhantomData,
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum DefLoaderError {
+    #[error("can't read def file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("can't parse RON def file: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("can't parse JSON def file: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("def {0} is declared twice in the same file")]
+    DuplicateDef(String),
+}
+
+impl<A> AssetLoader for DefLoader<A>
+where
+    A: Asset + DeserializeOwned,
+{
+    type Asset = A;
+    type Settings = ();
+    type Error = DefLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<A, DefLoaderError> {
+        deserialize(reader, self.json, load_context).await
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+/// The defs declared together in one file, loaded by [`DefFileLoader`].
+///
+/// Every def is a sub-asset labeled with its `defname`, like `stats.ron#health`, and is
+/// registered on its own. They stay loaded as long as the file is.
+#[derive(Asset, TypePath)]
+pub struct DefFile<A: Asset> {
+    #[dependency]
+    pub defs: Vec<Handle<A>>,
+}
+
+/// Loads a list of def assets from one RON or JSON file as a [`DefFile`], added with
+/// [`DefPlugin::with_file_loader`](crate::DefPlugin::with_file_loader).
+///
+/// Reloading the file updates every def in it.
+pub struct DefFileLoader<T: DefComponent> {
+    json: &'static [&'static str],
+    extensions: Vec<&'static str>,
+    register: fn(&mut App, Self),
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T> DefFileLoader<T>
+where
+    T: DefComponent,
+    T::Asset: DeserializeOwned,
+{
+    /// Extensions are given without the leading dot, and can have several parts like `"stats.ron"`.
+    pub fn new(ron: &'static [&'static str], json: &'static [&'static str]) -> Self {
+        Self {
+            json,
+            extensions: ron.iter().chain(json).copied().collect(),
+            register: |app, loader| {
+                app.init_asset::<DefFile<T::Asset>>();
+                app.register_asset_loader(loader);
+            },
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T: DefComponent> DefFileLoader<T> {
+    pub(crate) fn register(&self, app: &mut App) {
+        (self.register)(app, self.clone());
+    }
+}
+
+impl<T: DefComponent> Clone for DefFileLoader<T> {
+    fn clone(&self) -> Self {
+        Self {
+            json: self.json,
+            extensions: self.extensions.clone(),
+            register: self.register,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> AssetLoader for DefFileLoader<T>
+where
+    T: DefComponent,
+    T::Asset: DeserializeOwned,
+{
+    type Asset = DefFile<T::Asset>;
+    type Settings = ();
+    type Error = DefLoaderError;
+
+    async fn load(
+        &self,
+        reader: &mut dyn Reader,
+        _settings: &(),
+        load_context: &mut LoadContext<'_>,
+    ) -> Result<DefFile<T::Asset>, DefLoaderError> {
+        let assets: Vec<T::Asset> = deserialize(reader, self.json, load_context).await?;
+
+        let mut labels = HashSet::new();
+        let mut defs = Vec::with_capacity(assets.len());
+        for asset in assets {
+            let label = T::defname(&asset).into_owned();
+            if !labels.insert(label.clone()) {
+                return Err(DefLoaderError::DuplicateDef(label));
+            }
+            defs.push(load_context.add_labeled_asset(label, asset));
+        }
+
+        Ok(DefFile { defs })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &self.extensions
+    }
+}
+
+/// Reads JSON for files with one of the `json` extensions and RON for the rest.
+async fn deserialize<D: DeserializeOwned>(
+    reader: &mut dyn Reader,
+    json: &[&str],
+    load_context: &LoadContext<'_>,
+) -> Result<D, DefLoaderError> {
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes).await?;
+
+    let path = load_context.path().to_string_lossy();
+    let json = json
+        .iter()
+        .any(|extension| path.ends_with(&format!(".{extension}")));
+
+    if json {
+        Ok(serde_json::from_slice(&bytes)?)
+    } else {
+        Ok(ron::de::from_bytes(&bytes)?)
+    }
+}
@@ -0,0 +1,167 @@
+use crate::{DefComponent, DefIndex};
+use bevy_asset::{AssetId, Assets, Handle};
+use bevy_ecs::{component::ComponentId, world::World};
+use bevy_log::info;
+use std::borrow::Cow;
+
+/// Defs that can be changed by patch assets, enabled with [`DefPlugin::with_patches`](crate::DefPlugin::with_patches).
+///
+/// A patch is an asset with the `defname` of the def it changes. Instead of colliding with it,
+/// it's merged into the def, and [`DefRef::asset`](crate::DefRef::asset) shows the result.
+/// Entities keep the [`ComponentId`] of the def.
+pub trait DefPatch: DefComponent {
+    /// The layer of a patch, or `None` for the base asset of a def.
+    /// Patches are applied from the lowest layer up, and in the order they were added within a layer.
+    fn patch_layer(asset: &Self::Asset) -> Option<i32>;
+
+    /// Builds the asset of a def from its base and its patches, in order.
+    fn merge(base: &Self::Asset, patches: &[&Self::Asset]) -> Self::Asset;
+}
+
+pub(crate) struct PatchFns<T: DefComponent> {
+    layer: fn(&T::Asset) -> Option<i32>,
+    merge: fn(&T::Asset, &[&T::Asset]) -> T::Asset,
+}
+
+impl<T: DefComponent> Clone for PatchFns<T> {
+    fn clone(&self) -> Self {
+        Self {
+            layer: self.layer,
+            merge: self.merge,
+        }
+    }
+}
+
+impl<T: DefPatch> Default for PatchFns<T> {
+    fn default() -> Self {
+        Self {
+            layer: T::patch_layer,
+            merge: T::merge,
+        }
+    }
+}
+
+pub(crate) struct Patch<T: DefComponent> {
+    id: AssetId<T::Asset>,
+    name: Cow<'static, str>,
+    layer: i32,
+    order: u64,
+}
+
+/// A def with patches, its asset is a merged copy of the base.
+pub(crate) struct Merged<T: DefComponent> {
+    base: AssetId<T::Asset>,
+    handle: Handle<T::Asset>,
+}
+
+impl<T: DefComponent> DefIndex<T> {
+    /// Records `id` and rebuilds the def it patches. Returns `false` if `id` isn't a patch.
+    pub(crate) fn add_patch(&mut self, world: &mut World, id: AssetId<T::Asset>) -> bool {
+        let Some(patching) = &self.patching else {
+            return false;
+        };
+        let Some(asset) = world.resource::<Assets<T::Asset>>().get(id) else {
+            return false;
+        };
+        let Some(layer) = (patching.layer)(asset) else {
+            return false;
+        };
+
+        let name = T::defname(asset);
+        info!("patch {id} of def {name} on layer {layer}");
+
+        self.patch_order += 1;
+        self.patches.push(Patch {
+            id,
+            name: name.clone(),
+            layer,
+            order: self.patch_order,
+        });
+
+        self.rebuild(world, &name);
+        true
+    }
+
+    /// Forgets the patch `id` and rebuilds its def. Returns `false` if `id` isn't a patch.
+    pub(crate) fn remove_patch(&mut self, world: &mut World, id: AssetId<T::Asset>) -> bool {
+        let Some(index) = self.patches.iter().position(|patch| patch.id == id) else {
+            return false;
+        };
+
+        let patch = self.patches.remove(index);
+        self.rebuild(world, &patch.name);
+        true
+    }
+
+    pub(crate) fn is_merged(&self, id: AssetId<T::Asset>) -> bool {
+        self.merged.values().any(|merged| merged.handle.id() == id)
+    }
+
+    /// The component of the def if `id` is the base of a merged def.
+    pub(crate) fn merged_base(&self, id: AssetId<T::Asset>) -> Option<ComponentId> {
+        let component_id = *self.asset_to_id.get(&id)?;
+        let merged = self.merged.get(&component_id)?;
+        (merged.base == id).then_some(component_id)
+    }
+
+    /// Merges the patches of the def `name` into a new asset, or goes back to the base without them.
+    pub(crate) fn rebuild(&mut self, world: &mut World, name: &str) {
+        let Some(merge) = self.patching.as_ref().map(|patching| patching.merge) else {
+            return;
+        };
+        let Some((name, &(id, component_id))) = self.names.get_key_value(name) else {
+            // The base isn't registered yet, patches are applied once it is.
+            return;
+        };
+        let name = name.clone();
+
+        let mut patches: Vec<_> = self
+            .patches
+            .iter()
+            .filter(|patch| patch.name == name)
+            .collect();
+
+        if patches.is_empty() {
+            self.unmerge(component_id);
+            return;
+        }
+
+        patches.sort_by_key(|patch| (patch.layer, patch.order));
+        let patches: Vec<_> = patches.into_iter().map(|patch| patch.id).collect();
+
+        let base = self
+            .merged
+            .get(&component_id)
+            .map_or(id, |merged| merged.base);
+
+        let mut assets = world.resource_mut::<Assets<T::Asset>>();
+        let Some(base_asset) = assets.get(base) else {
+            return;
+        };
+        let patch_assets: Vec<_> = patches.iter().filter_map(|&id| assets.get(id)).collect();
+        let merged = merge(base_asset, &patch_assets);
+
+        info!("def {name} is {base} with {} patches", patches.len());
+
+        match self.merged.get(&component_id) {
+            Some(Merged { handle, .. }) => assets.insert(handle, merged),
+            None => {
+                let handle = assets.add(merged);
+                self.link(name, handle.id(), component_id);
+                self.merged.insert(component_id, Merged { base, handle });
+            }
+        }
+    }
+
+    /// Makes the base asset the asset of the def again.
+    pub(crate) fn unmerge(&mut self, component_id: ComponentId) {
+        let Some(Merged { base, handle }) = self.merged.remove(&component_id) else {
+            return;
+        };
+
+        self.asset_to_id.remove(&handle.id());
+        if let Some(name) = self.name(handle.id()).cloned() {
+            self.link(name, base, component_id);
+        }
+    }
+}
@@ -0,0 +1,406 @@
+use crate::{DefComponent, DefEntityMut, DefEntityRef, DefIndex, DefMut, DefRef, DefSelector};
+use bevy_asset::Assets;
+use bevy_ecs::{
+    archetype::{Archetype, ArchetypeId},
+    change_detection::MaybeLocation,
+    component::{ComponentId, StorageType, Tick, TickCells},
+    entity::Entity,
+    query::{Access, QueryData, QueryState},
+    storage::TableId,
+    system::{Query, ReadOnlySystemParam, SystemChangeTick, SystemMeta, SystemParam},
+    world::{Mut, World, unsafe_world_cell::UnsafeWorldCell},
+};
+use bevy_platform::collections::HashMap;
+use std::{cell::UnsafeCell, marker::PhantomData, panic::Location};
+
+type RawDef<'w, T> = (
+    Entity,
+    *mut T,
+    TickCells<'w>,
+    MaybeLocation<&'w UnsafeCell<&'static Location<'static>>>,
+);
+
+/// State of [`DefQuery`] and [`DefQueryMut`]: the inner query, and the tables or
+/// archetypes that contain each def component it has access to.
+pub struct DefQueryParamState<T: DefComponent, D: QueryData> {
+    query: QueryState<D>,
+    matched: HashMap<ComponentId, Vec<Matched>>,
+    marker: PhantomData<fn() -> T>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Matched {
+    Table(TableId),
+    Archetype(ArchetypeId),
+}
+
+impl<T: DefComponent, D: QueryData> DefQueryParamState<T, D> {
+    pub fn query(&self) -> &QueryState<D> {
+        &self.query
+    }
+
+    fn new(query: QueryState<D>) -> Self {
+        Self {
+            query,
+            matched: HashMap::default(),
+            marker: PhantomData,
+        }
+    }
+
+    /// Records `archetype` under the def components in it, so iterating one def
+    /// doesn't walk every table and archetype in the world.
+    fn new_archetype(&mut self, archetype: &Archetype) {
+        let access = self.query.component_access().access();
+
+        for component_id in archetype.components() {
+            if !access.has_component_read(component_id) {
+                continue;
+            }
+
+            let matched = match T::STORAGE_TYPE {
+                StorageType::Table => Matched::Table(archetype.table_id()),
+                StorageType::SparseSet => Matched::Archetype(archetype.id()),
+            };
+            let list = self.matched.entry(component_id).or_default();
+            if !list.contains(&matched) {
+                list.push(matched);
+            }
+        }
+    }
+
+    fn matched(&self, component_id: ComponentId) -> &[Matched] {
+        self.matched.get(&component_id).map_or(&[], Vec::as_slice)
+    }
+}
+
+/// Read-only [`SystemParam`] over the entities that have the def picked by `S`.
+///
+/// The def is resolved once per run and only the tables or archetypes that contain it
+/// are visited. Defs stored in tables are read straight from their columns.
+pub struct DefQuery<'w, 's, T: DefComponent, S: DefSelector<T>> {
+    world: UnsafeWorldCell<'w>,
+    state: &'s DefQueryParamState<T, DefEntityRef<'static, T>>,
+    def: Option<(ComponentId, &'w T::Asset)>,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<'w, 's, T: DefComponent, S: DefSelector<T>> DefQuery<'w, 's, T, S> {
+    pub fn component_id(&self) -> Option<ComponentId> {
+        self.def.map(|(component_id, _)| component_id)
+    }
+
+    pub fn asset(&self) -> Option<&'w T::Asset> {
+        self.def.map(|(_, asset)| asset)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, DefRef<'_, 'w, T>)> + '_ {
+        self.def.into_iter().flat_map(|(component_id, asset)| {
+            // SAFETY: read access to the claimed def components has been registered.
+            unsafe { raw_iter::<T, _>(self.world, self.state, component_id) }.map(
+                move |(entity, value, _, _)| {
+                    // SAFETY: the pointer comes from a live column of `T` values.
+                    let value = unsafe { &*value };
+                    (entity, DefRef { value, asset })
+                },
+            )
+        })
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<DefRef<'_, 'w, T>> {
+        let (component_id, asset) = self.def?;
+        let entity = self.world.get_entity(entity).ok()?;
+        // SAFETY: read access to the claimed def components has been registered.
+        let value = unsafe { entity.get_by_id(component_id)?.deref() };
+        Some(DefRef { value, asset })
+    }
+}
+
+/// [`SystemParam`] over the entities that have the def picked by `S`, with mutable access.
+///
+/// See [`DefQuery`].
+pub struct DefQueryMut<'w, 's, T: DefComponent, S: DefSelector<T>> {
+    world: UnsafeWorldCell<'w>,
+    state: &'s DefQueryParamState<T, DefEntityMut<'static, T>>,
+    def: Option<(ComponentId, &'w T::Asset)>,
+    last_run: Tick,
+    this_run: Tick,
+    marker: PhantomData<fn() -> S>,
+}
+
+impl<'w, 's, T: DefComponent, S: DefSelector<T>> DefQueryMut<'w, 's, T, S> {
+    pub fn component_id(&self) -> Option<ComponentId> {
+        self.def.map(|(component_id, _)| component_id)
+    }
+
+    pub fn asset(&self) -> Option<&'w T::Asset> {
+        self.def.map(|(_, asset)| asset)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, DefRef<'_, 'w, T>)> + '_ {
+        self.def.into_iter().flat_map(|(component_id, asset)| {
+            // SAFETY: mutable access to the claimed def components has been registered.
+            unsafe { raw_iter::<T, _>(self.world, self.state, component_id) }.map(
+                move |(entity, value, _, _)| {
+                    // SAFETY: the pointer comes from a live column of `T` values.
+                    let value = unsafe { &*value };
+                    (entity, DefRef { value, asset })
+                },
+            )
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (Entity, DefMut<'_, 'w, T>)> + '_ {
+        let (last_run, this_run) = (self.last_run, self.this_run);
+
+        self.def.into_iter().flat_map(move |(component_id, asset)| {
+            // SAFETY: mutable access to the claimed def components has been registered,
+            // and each entity is visited once, so the values don't alias.
+            unsafe { raw_iter::<T, _>(self.world, self.state, component_id) }.map(
+                move |(entity, value, ticks, changed_by)| {
+                    // SAFETY: the pointers come from live columns, see above.
+                    let value = unsafe {
+                        Mut::new(
+                            &mut *value,
+                            &mut *ticks.added.get(),
+                            &mut *ticks.changed.get(),
+                            last_run,
+                            this_run,
+                            changed_by.map(|changed_by| &mut *changed_by.get()),
+                        )
+                    };
+                    (entity, DefMut { value, asset })
+                },
+            )
+        })
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<DefRef<'_, 'w, T>> {
+        let (component_id, asset) = self.def?;
+        let entity = self.world.get_entity(entity).ok()?;
+        // SAFETY: mutable access to the claimed def components has been registered.
+        let value = unsafe { entity.get_by_id(component_id)?.deref() };
+        Some(DefRef { value, asset })
+    }
+
+    pub fn get_mut(&mut self, entity: Entity) -> Option<DefMut<'_, 'w, T>> {
+        let (component_id, asset) = self.def?;
+        // SAFETY: mutable access to the claimed def components has been registered,
+        // and `&mut self` prevents other borrows of this query.
+        let (_, value, ticks, changed_by) =
+            unsafe { raw_get::<T>(self.world, component_id, entity)? };
+        // SAFETY: the pointers come from a live column or sparse set, see above.
+        // Ticks are those of the system, like in `iter_mut`.
+        let value = unsafe {
+            Mut::new(
+                &mut *value,
+                &mut *ticks.added.get(),
+                &mut *ticks.changed.get(),
+                self.last_run,
+                self.this_run,
+                changed_by.map(|changed_by| &mut *changed_by.get()),
+            )
+        };
+        Some(DefMut { value, asset })
+    }
+}
+
+/// # Safety
+/// Access to the def component must be registered for `state`,
+/// and the returned pointers must not outlive `'w`.
+unsafe fn raw_iter<'w: 's, 's, T: DefComponent, D: QueryData>(
+    world: UnsafeWorldCell<'w>,
+    state: &'s DefQueryParamState<T, D>,
+    component_id: ComponentId,
+) -> impl Iterator<Item = RawDef<'w, T>> + 's {
+    // SAFETY: only the storage of the def component is touched, see the caller.
+    let storages = unsafe { world.storages() };
+
+    let matched = state.matched(component_id);
+
+    let tables = matched
+        .iter()
+        .filter_map(|&matched| match matched {
+            Matched::Table(table_id) => Some(&storages.tables[table_id]),
+            Matched::Archetype(_) => None,
+        })
+        .flat_map(move |table| {
+            let len = table.entity_count();
+            table
+                .get_column(component_id)
+                .into_iter()
+                .flat_map(move |column| {
+                    // SAFETY: the column stores `T` and has `len` rows.
+                    let (values, added, changed, changed_by) = unsafe {
+                        (
+                            column.get_data_slice::<T>(len),
+                            column.get_added_ticks_slice(len),
+                            column.get_changed_ticks_slice(len),
+                            column.get_changed_by_slice(len),
+                        )
+                    };
+                    table
+                        .entities()
+                        .iter()
+                        .enumerate()
+                        .map(move |(row, &entity)| {
+                            let ticks = TickCells {
+                                added: &added[row],
+                                changed: &changed[row],
+                            };
+                            let changed_by = changed_by.map(|changed_by| &changed_by[row]);
+                            (entity, values[row].get(), ticks, changed_by)
+                        })
+                })
+        });
+
+    let sparse_set = storages.sparse_sets.get(component_id);
+    let archetypes = matched
+        .iter()
+        .filter_map(move |&matched| match matched {
+            Matched::Table(_) => None,
+            Matched::Archetype(archetype_id) => Some(&world.archetypes()[archetype_id]),
+        })
+        .flat_map(move |archetype| {
+            archetype.entities().iter().filter_map(move |entity| {
+                let entity = entity.id();
+                let (value, ticks, changed_by) = sparse_set?.get_with_ticks(entity)?;
+                Some((entity, value.as_ptr().cast::<T>(), ticks, changed_by))
+            })
+        });
+
+    tables.chain(archetypes)
+}
+
+/// # Safety
+/// Access to the def component must be registered,
+/// and the returned pointers must not outlive `'w`.
+unsafe fn raw_get<'w, T: DefComponent>(
+    world: UnsafeWorldCell<'w>,
+    component_id: ComponentId,
+    entity: Entity,
+) -> Option<RawDef<'w, T>> {
+    let location = world.entities().get(entity)?;
+    // SAFETY: only the storage of the def component is touched, see the caller.
+    let storages = unsafe { world.storages() };
+
+    let (value, ticks, changed_by) = match T::STORAGE_TYPE {
+        StorageType::Table => {
+            let (table, row) = (&storages.tables[location.table_id], location.table_row);
+            // SAFETY: the row of a live entity is in bounds.
+            let value = unsafe { table.get_component(component_id, row)? };
+            let ticks = TickCells {
+                added: table.get_added_tick(component_id, row)?,
+                changed: table.get_changed_tick(component_id, row)?,
+            };
+            let changed_by = table.get_changed_by(component_id, row).transpose()?;
+            (value, ticks, changed_by)
+        }
+        StorageType::SparseSet => storages
+            .sparse_sets
+            .get(component_id)?
+            .get_with_ticks(entity)?,
+    };
+
+    Some((entity, value.as_ptr().cast::<T>(), ticks, changed_by))
+}
+
+fn resolve<'w, T: DefComponent, S: DefSelector<T>>(
+    world: UnsafeWorldCell<'w>,
+    access: &Access<ComponentId>,
+) -> Option<(ComponentId, &'w T::Asset)> {
+    // SAFETY: read access to both resources has been registered by the inner query.
+    let (index, assets) = unsafe {
+        (
+            world.get_resource::<DefIndex<T>>()?,
+            world.get_resource::<Assets<T::Asset>>()?,
+        )
+    };
+    let component_id = S::select(index)?;
+    // Looking the asset up also checks that `S` picked a component of this index.
+    let asset = assets.get(*index.id_to_asset().get(&component_id)?)?;
+    // Defs registered after the system was built aren't covered by its access.
+    access
+        .has_component_read(component_id)
+        .then_some((component_id, asset))
+}
+
+/// SAFETY: all access is registered by the inner `Query<DefEntityRef<T>>`.
+unsafe impl<T: DefComponent, S: DefSelector<T>> SystemParam for DefQuery<'_, '_, T, S> {
+    type State = DefQueryParamState<T, DefEntityRef<'static, T>>;
+    type Item<'w, 's> = DefQuery<'w, 's, T, S>;
+
+    fn init_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::State {
+        DefQueryParamState::new(Query::<DefEntityRef<'static, T>>::init_state(world, system_meta))
+    }
+
+    unsafe fn new_archetype(
+        state: &mut Self::State,
+        archetype: &Archetype,
+        system_meta: &mut SystemMeta,
+    ) {
+        // SAFETY: forwarded from the caller.
+        unsafe {
+            Query::<DefEntityRef<'static, T>>::new_archetype(&mut state.query, archetype, system_meta);
+        }
+        state.new_archetype(archetype);
+    }
+
+    unsafe fn get_param<'w, 's>(
+        state: &'s mut Self::State,
+        _system_meta: &SystemMeta,
+        world: UnsafeWorldCell<'w>,
+        _change_tick: Tick,
+    ) -> Self::Item<'w, 's> {
+        DefQuery {
+            world,
+            state,
+            def: resolve::<T, S>(world, state.query.component_access().access()),
+            marker: PhantomData,
+        }
+    }
+}
+
+/// SAFETY: only read access is registered.
+unsafe impl<T: DefComponent, S: DefSelector<T>> ReadOnlySystemParam for DefQuery<'_, '_, T, S> {}
+
+/// SAFETY: all access is registered by the inner `Query<DefEntityMut<T>>`.
+unsafe impl<T: DefComponent, S: DefSelector<T>> SystemParam for DefQueryMut<'_, '_, T, S> {
+    type State = DefQueryParamState<T, DefEntityMut<'static, T>>;
+    type Item<'w, 's> = DefQueryMut<'w, 's, T, S>;
+
+    fn init_state(world: &mut World, system_meta: &mut SystemMeta) -> Self::State {
+        DefQueryParamState::new(Query::<DefEntityMut<'static, T>>::init_state(world, system_meta))
+    }
+
+    unsafe fn new_archetype(
+        state: &mut Self::State,
+        archetype: &Archetype,
+        system_meta: &mut SystemMeta,
+    ) {
+        // SAFETY: forwarded from the caller.
+        unsafe {
+            Query::<DefEntityMut<'static, T>>::new_archetype(&mut state.query, archetype, system_meta);
+        }
+        state.new_archetype(archetype);
+    }
+
+    unsafe fn get_param<'w, 's>(
+        state: &'s mut Self::State,
+        system_meta: &SystemMeta,
+        world: UnsafeWorldCell<'w>,
+        change_tick: Tick,
+    ) -> Self::Item<'w, 's> {
+        // SAFETY: `SystemChangeTick` doesn't access the world.
+        let ticks =
+            unsafe { SystemChangeTick::get_param(&mut (), system_meta, world, change_tick) };
+
+        DefQueryMut {
+            world,
+            state,
+            def: resolve::<T, S>(world, state.query.component_access().access()),
+            last_run: ticks.last_run(),
+            this_run: ticks.this_run(),
+            marker: PhantomData,
+        }
+    }
+}
@@ -0,0 +1,155 @@
+use crate::{DefComponent, DefError, DefIndex, commands::insert_by_id};
+use bevy_ecs::{
+    change_detection::Mut,
+    component::ComponentId,
+    entity::Entity,
+    resource::Resource,
+    world::{EntityWorldMut, World},
+};
+use bevy_platform::collections::HashMap;
+use bevy_reflect::{FromReflect, FromType, PartialReflect, Reflect};
+use std::{
+    any::{TypeId, type_name},
+    borrow::Cow,
+};
+
+type DefList = Vec<(Cow<'static, str>, ComponentId)>;
+
+/// The Rust type of every def component, filled by [`DefIndex`] for every def it registers.
+///
+/// Def components have no [`TypeId`] in their descriptor. Tools that find one on an entity
+/// look its type up here, and then the [`ReflectDefComponent`] of that type in the registry.
+#[derive(Resource, Default)]
+pub struct DefComponentTypes {
+    types: HashMap<ComponentId, TypeId>,
+}
+
+impl DefComponentTypes {
+    pub(crate) fn insert<T: DefComponent>(&mut self, component_id: ComponentId) {
+        self.types.insert(component_id, TypeId::of::<T>());
+    }
+
+    pub fn get(&self, component_id: ComponentId) -> Option<TypeId> {
+        self.types.get(&component_id).copied()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (ComponentId, TypeId)> + '_ {
+        self.types
+            .iter()
+            .map(|(&component_id, &type_id)| (component_id, type_id))
+    }
+}
+
+/// Type data to read and write def components of a reflected type,
+/// added with `#[reflect(DefComponent)]` and [`App::register_type`](bevy_app::App::register_type).
+///
+/// Every method checks that the component was registered by [`DefIndex`] of that type.
+#[derive(Clone)]
+pub struct ReflectDefComponent {
+    defs: fn(&World) -> DefList,
+    contains: fn(&World, ComponentId) -> bool,
+    reflect: for<'a> fn(&'a World, Entity, ComponentId) -> Option<&'a dyn Reflect>,
+    reflect_mut:
+        for<'a> fn(&'a mut EntityWorldMut<'_>, ComponentId) -> Option<Mut<'a, dyn Reflect>>,
+    insert: fn(&mut EntityWorldMut<'_>, ComponentId, &dyn PartialReflect) -> Result<(), DefError>,
+}
+
+impl ReflectDefComponent {
+    /// Names and components of every registered def of this type.
+    pub fn defs(&self, world: &World) -> Vec<(Cow<'static, str>, ComponentId)> {
+        (self.defs)(world)
+    }
+
+    pub fn component_id(&self, world: &World, name: &str) -> Option<ComponentId> {
+        self.defs(world)
+            .into_iter()
+            .find_map(|(def, component_id)| (def == name).then_some(component_id))
+    }
+
+    /// Whether `component_id` is a def component of this type, orphaned ones included.
+    pub fn contains(&self, world: &World, component_id: ComponentId) -> bool {
+        (self.contains)(world, component_id)
+    }
+
+    pub fn reflect<'a>(
+        &self,
+        world: &'a World,
+        entity: Entity,
+        component_id: ComponentId,
+    ) -> Option<&'a dyn Reflect> {
+        (self.reflect)(world, entity, component_id)
+    }
+
+    pub fn reflect_mut<'a>(
+        &self,
+        entity: &'a mut EntityWorldMut<'_>,
+        component_id: ComponentId,
+    ) -> Option<Mut<'a, dyn Reflect>> {
+        (self.reflect_mut)(entity, component_id)
+    }
+
+    /// Inserts the def component `component_id` built with [`FromReflect`] from `value`.
+    pub fn insert(
+        &self,
+        entity: &mut EntityWorldMut<'_>,
+        component_id: ComponentId,
+        value: &dyn PartialReflect,
+    ) -> Result<(), DefError> {
+        (self.insert)(entity, component_id, value)
+    }
+}
+
+impl<T: DefComponent + Reflect + FromReflect> FromType<T> for ReflectDefComponent {
+    fn from_type() -> Self {
+        Self {
+            defs: |world| {
+                world
+                    .get_resource::<DefIndex<T>>()
+                    .into_iter()
+                    .flat_map(|index| index.names())
+                    .map(|(name, &(_, component_id))| (name.clone(), component_id))
+                    .collect()
+            },
+            contains: contains::<T>,
+            reflect: |world, entity, component_id| {
+                if !contains::<T>(world, component_id) {
+                    return None;
+                }
+                let ptr = world.get_by_id(entity, component_id)?;
+                // SAFETY: checked above that the component stores `T`.
+                Some(unsafe { ptr.deref::<T>() })
+            },
+            reflect_mut: |entity, component_id| {
+                if !contains::<T>(entity.world(), component_id) {
+                    return None;
+                }
+                let value = entity.get_mut_by_id(component_id).ok()?;
+                // SAFETY: checked above that the component stores `T`.
+                Some(
+                    value.map_unchanged(|ptr| -> &mut dyn Reflect {
+                        unsafe { ptr.deref_mut::<T>() }
+                    }),
+                )
+            },
+            insert: |entity, component_id, value| {
+                if !contains::<T>(entity.world(), component_id) {
+                    return Err(DefError::UnknownComponent {
+                        ty: type_name::<T>(),
+                        component_id,
+                    });
+                }
+                let value = T::from_reflect(value).ok_or(DefError::FromReflect {
+                    ty: type_name::<T>(),
+                })?;
+                insert_by_id(entity, component_id, value);
+                Ok(())
+            },
+        }
+    }
+}
+
+fn contains<T: DefComponent>(world: &World, component_id: ComponentId) -> bool {
+    world
+        .get_resource::<DefIndex<T>>()
+        .is_some_and(|index| index.contains_component(component_id))
+}
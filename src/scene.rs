@@ -0,0 +1,147 @@
+use crate::{DefComponent, DefIndex, InsertDef};
+use bevy_app::{App, Plugin};
+use bevy_ecs::{
+    component::{Component, ComponentHook, Mutable, StorageType},
+    entity::EntityMapper,
+    reflect::ReflectComponent,
+    system::EntityCommand,
+    world::{EntityRef, EntityWorldMut, World},
+};
+use bevy_log::warn;
+use bevy_reflect::{FromReflect, GetTypeRegistration, Reflect, TypePath, Typed};
+use bevy_scene::DynamicScene;
+use std::{collections::BTreeMap, marker::PhantomData};
+
+/// Def components of type `T` of one entity as they are stored in a scene, keyed by `defname`.
+///
+/// [`ComponentId`](bevy_ecs::component::ComponentId)s of defs depend on the order their assets
+/// were added, so scenes store this component instead. When it's inserted, like when a scene
+/// is spawned, every value is resolved through [`DefIndex<T>`] by name and inserted as the
+/// def component. Defs that aren't registered yet are applied once they are,
+/// see [`InsertDef::deferred`].
+#[derive(Reflect)]
+#[reflect(Component)]
+pub struct SceneDefs<T: DefComponent> {
+    pub defs: BTreeMap<String, T>,
+}
+
+impl<T: DefComponent> Default for SceneDefs<T> {
+    fn default() -> Self {
+        Self {
+            defs: BTreeMap::new(),
+        }
+    }
+}
+
+impl<T: DefComponent> Component for SceneDefs<T> {
+    const STORAGE_TYPE: StorageType = StorageType::Table;
+    type Mutability = Mutable;
+
+    fn on_insert() -> Option<ComponentHook> {
+        Some(|mut world, context| {
+            world
+                .commands()
+                .entity(context.entity)
+                .queue(apply_scene_defs::<T>);
+        })
+    }
+
+    fn map_entities<E: EntityMapper>(this: &mut Self, mapper: &mut E) {
+        for value in this.defs.values_mut() {
+            T::map_entities(value, mapper);
+        }
+    }
+}
+
+impl<T> SceneDefs<T>
+where
+    T: DefComponent + FromReflect,
+{
+    /// Copies every def of type `T` from `entity`, orphaned ones are left out.
+    pub fn from_entity(world: &World, entity: EntityRef<'_>) -> Self {
+        let mut defs = BTreeMap::new();
+
+        if let Some(index) = world.get_resource::<DefIndex<T>>() {
+            for component_id in entity.archetype().components() {
+                let Some(asset_id) = index.id_to_asset().get(&component_id) else {
+                    continue;
+                };
+                let Some(name) = index.name(*asset_id) else {
+                    continue;
+                };
+                let Some(value) = entity.get_by_id(component_id).ok() else {
+                    continue;
+                };
+                // SAFETY: the component was registered by the index, so it stores `T`.
+                let value = unsafe { value.deref::<T>() };
+                match T::from_reflect(value) {
+                    Some(value) => {
+                        defs.insert(name.to_string(), value);
+                    }
+                    None => warn!("can't copy def {name} of {} for a scene", entity.id()),
+                }
+            }
+        }
+
+        Self { defs }
+    }
+}
+
+fn apply_scene_defs<T: DefComponent>(mut entity: EntityWorldMut<'_>) {
+    let Some(scene_defs) = entity.take::<SceneDefs<T>>() else {
+        return;
+    };
+
+    let id = entity.id();
+    let world = entity.into_world_mut();
+
+    for (name, value) in scene_defs.defs {
+        let insert = InsertDef::by_name(name, value).deferred();
+        if let Err(err) = insert.apply(world.entity_mut(id)) {
+            warn!("can't load def for {id} from a scene: {err}");
+        }
+    }
+}
+
+/// Adds the defs of type `T` of every entity in `scene` as [`SceneDefs<T>`].
+///
+/// [`DynamicSceneBuilder`](bevy_scene::DynamicSceneBuilder) only extracts components with a
+/// Rust type, so def components have to be added to a built scene with this function.
+pub fn extract_scene_defs<T>(scene: &mut DynamicScene, world: &World)
+where
+    T: DefComponent + FromReflect + TypePath + Typed + GetTypeRegistration,
+{
+    for scene_entity in &mut scene.entities {
+        let Ok(entity) = world.get_entity(scene_entity.entity) else {
+            continue;
+        };
+
+        let defs = SceneDefs::<T>::from_entity(world, entity);
+        if !defs.defs.is_empty() {
+            scene_entity.components.push(Box::new(defs));
+        }
+    }
+}
+
+/// Registers [`SceneDefs<T>`] so scenes can save and load defs of type `T`.
+pub struct DefScenePlugin<T: DefComponent> {
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<T: DefComponent> Default for DefScenePlugin<T> {
+    fn default() -> Self {
+        Self {
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<T> Plugin for DefScenePlugin<T>
+where
+    T: DefComponent + FromReflect + TypePath + Typed + GetTypeRegistration,
+{
+    fn build(&self, app: &mut App) {
+        app.register_type::<T>();
+        app.register_type::<SceneDefs<T>>();
+    }
+}
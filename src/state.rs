@@ -0,0 +1,107 @@
+use crate::{DefComponent, DefFolders, DefIndex, def_folder_system};
+use bevy_app::{App, PostUpdate};
+use bevy_ecs::{
+    resource::Resource,
+    schedule::IntoScheduleConfigs,
+    system::{Res, ResMut},
+};
+use bevy_log::{info, warn};
+use bevy_platform::collections::HashSet;
+use bevy_state::{
+    condition::in_state,
+    state::{FreelyMutableState, NextState},
+};
+use std::any::{TypeId, type_name};
+
+/// Adds the systems of [`DefLoadingState`] for one def type and state type.
+pub(crate) type AddLoadingState = Box<dyn Fn(&mut App) + Send + Sync>;
+
+/// Keeps the app in a loading state until the defs of every [`DefPlugin`](crate::DefPlugin)
+/// added with [`with_loading_state`](crate::DefPlugin::with_loading_state) are registered,
+/// and then moves it to the next state.
+///
+/// Defs of a type are registered once every folder in [`DefFolders`] is ready and
+/// [`DefIndex::is_loaded`], so def assets loaded outside of a folder have to be passed
+/// to [`DefIndex::request`]. Without folders or requests, the state moves on
+/// as soon as the defs that are already there are registered.
+#[derive(Resource)]
+pub struct DefLoadingState<S: FreelyMutableState> {
+    loading: S,
+    next: S,
+    waiting: HashSet<TypeId>,
+}
+
+impl<S: FreelyMutableState> DefLoadingState<S> {
+    pub fn loading(&self) -> &S {
+        &self.loading
+    }
+
+    pub fn next(&self) -> &S {
+        &self.next
+    }
+
+    /// Number of def types that aren't registered yet.
+    pub fn waiting(&self) -> usize {
+        self.waiting.len()
+    }
+}
+
+pub(crate) fn add_loading_state<T, S>(app: &mut App, loading: S, next: S)
+where
+    T: DefComponent,
+    S: FreelyMutableState,
+{
+    match app.world_mut().get_resource_mut::<DefLoadingState<S>>() {
+        Some(mut state) => {
+            if state.loading != loading || state.next != next {
+                warn!(
+                    "defs of `{}` use the loading states of the first def plugin",
+                    type_name::<T>()
+                );
+            }
+            state.waiting.insert(TypeId::of::<T>());
+        }
+        None => {
+            app.insert_resource(DefLoadingState {
+                loading: loading.clone(),
+                next,
+                waiting: HashSet::from_iter([TypeId::of::<T>()]),
+            });
+            app.add_systems(
+                PostUpdate,
+                def_loading_state_system::<S>.run_if(in_state(loading.clone())),
+            );
+        }
+    }
+
+    app.add_systems(
+        PostUpdate,
+        def_loading_ready_system::<T, S>
+            .run_if(in_state(loading))
+            .after(def_folder_system::<T>)
+            .before(def_loading_state_system::<S>),
+    );
+}
+
+fn def_loading_ready_system<T, S>(
+    folders: Res<DefFolders<T>>,
+    index: Res<DefIndex<T>>,
+    mut state: ResMut<DefLoadingState<S>>,
+) where
+    T: DefComponent,
+    S: FreelyMutableState,
+{
+    if folders.is_ready() && index.is_loaded() {
+        state.waiting.remove(&TypeId::of::<T>());
+    }
+}
+
+fn def_loading_state_system<S: FreelyMutableState>(
+    state: Res<DefLoadingState<S>>,
+    mut next: ResMut<NextState<S>>,
+) {
+    if state.waiting.is_empty() {
+        info!("defs are registered, moving to {:?}", state.next);
+        next.set(state.next.clone());
+    }
+}
@@ -0,0 +1,52 @@
+mod common;
+
+use bevy::{
+    ecs::entity::{EntityCloner, EntityHashMap, EntityMapper},
+    prelude::*,
+};
+use bevy_def::*;
+use common::*;
+
+#[test]
+fn clones_copy_defs() {
+    let mut app = app(DefPlugin::default());
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let entity = spawn(&mut app, "hp", 7.0);
+
+    let clone = app.world_mut().entity_mut(entity).clone_and_spawn();
+
+    assert_eq!(value(&mut app, clone, "hp"), Some(7.0));
+    assert_eq!(value(&mut app, entity, "hp"), Some(7.0));
+}
+
+#[test]
+fn clones_map_entities_in_defs() {
+    let mut app = app(DefPlugin::default());
+    app.add_plugins(DefPlugin::<Bond>::default());
+    let _owner = app
+        .world_mut()
+        .resource_mut::<Assets<BondAsset>>()
+        .add(BondAsset::new("owner"));
+    app.update();
+
+    let target = app.world_mut().spawn_empty().id();
+    let entity = app.world_mut().spawn_empty().id();
+    app.world_mut()
+        .commands()
+        .entity(entity)
+        .queue(InsertDef::by_name("owner", Bond { target }));
+    app.world_mut().flush();
+
+    let clone = app.world_mut().spawn_empty().id();
+    let cloned_target = app.world_mut().spawn_empty().id();
+    let mut mapper = EntityHashMap::default();
+    mapper.set_mapped(entity, clone);
+    mapper.set_mapped(target, cloned_target);
+    EntityCloner::build(app.world_mut())
+        .finish()
+        .clone_entity_mapped(app.world_mut(), entity, &mut mapper);
+
+    let mut query = app.world_mut().query::<DefEntityRef<Bond>>();
+    let bonds = query.get(app.world(), clone).unwrap();
+    assert_eq!(bonds.find_ref("owner").unwrap().value.target, cloned_target);
+}
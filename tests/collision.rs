@@ -0,0 +1,77 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+fn collide(policy: DefCollisionPolicy) -> (App, Handle<StatAsset>, Handle<StatAsset>) {
+    let mut app = app(DefPlugin::default().with_collision_policy(policy));
+    let first = add(&mut app, StatAsset::new("hp", 10.0));
+    events(&mut app);
+    let second = add(&mut app, StatAsset::new("hp", 20.0));
+    (app, first, second)
+}
+
+fn collisions(app: &mut App) -> Vec<DefCollisionPolicy> {
+    events(app)
+        .into_iter()
+        .filter_map(|event| match event {
+            DefEvent::Collision { policy, .. } => Some(policy),
+            _ => None,
+        })
+        .collect()
+}
+
+#[test]
+fn error_keeps_the_first_asset() {
+    let (mut app, first, second) = collide(DefCollisionPolicy::Error);
+
+    assert_eq!(collisions(&mut app), [DefCollisionPolicy::Error]);
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert_eq!(index.find_by_name("hp").map(|(id, _)| id), Some(first.id()));
+    assert_eq!(index.asset_to_id().get(&second.id()), None);
+    assert_eq!(default_of(&app, "hp"), Some(10.0));
+}
+
+#[test]
+fn override_replaces_the_asset_and_keeps_the_component() {
+    let (mut app, first, second) = collide(DefCollisionPolicy::Override);
+
+    assert_eq!(collisions(&mut app), [DefCollisionPolicy::Override]);
+    let index = app.world().resource::<DefIndex<Stat>>();
+    let (id, component_id) = index.find_by_name("hp").unwrap();
+    assert_eq!(id, second.id());
+    assert_eq!(index.asset_to_id().get(&first.id()), None);
+    assert_eq!(index.id_to_asset().get(&component_id), Some(&second.id()));
+    assert_eq!(default_of(&app, "hp"), Some(20.0));
+}
+
+#[test]
+fn alias_shares_the_component() {
+    let (mut app, first, second) = collide(DefCollisionPolicy::Alias);
+
+    assert_eq!(collisions(&mut app), [DefCollisionPolicy::Alias]);
+    let index = app.world().resource::<DefIndex<Stat>>();
+    let (id, component_id) = index.find_by_name("hp").unwrap();
+    assert_eq!(id, first.id());
+    assert_eq!(index.asset_to_id().get(&second.id()), Some(&component_id));
+    assert_eq!(default_of(&app, "hp"), Some(10.0));
+}
+
+#[test]
+fn alias_takes_over_when_the_first_asset_is_removed() {
+    let (mut app, first, second) = collide(DefCollisionPolicy::Alias);
+    let component_id = component_of(&app, "hp");
+    let entity = spawn(&mut app, "hp", 1.0);
+
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .remove(&first);
+    app.update();
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert_eq!(index.find_by_name("hp").map(|(id, _)| id), Some(second.id()));
+    assert_eq!(component_of(&app, "hp"), component_id);
+    assert_eq!(default_of(&app, "hp"), Some(20.0));
+    assert_eq!(value(&mut app, entity, "hp"), Some(1.0));
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use bevy_def::*;
+use common::*;
+
+#[test]
+fn deferred_insertions_keep_their_order() {
+    let mut app = app(DefPlugin::default());
+
+    let mut commands = app.world_mut().commands();
+    let entity = commands
+        .spawn_empty()
+        .queue(InsertDef::by_name("hp", Stat { current: 1.0 }).deferred())
+        .queue(InsertDef::by_name("hp", Stat { current: 2.0 }).deferred())
+        .queue(InsertDef::by_name("hp", Stat { current: 3.0 }).deferred())
+        .id();
+    app.world_mut().flush();
+    assert_eq!(
+        app.world().resource::<DefIndex<Stat>>().queued_insertions(),
+        3
+    );
+
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+
+    assert_eq!(value(&mut app, entity, "hp"), Some(3.0));
+    assert_eq!(
+        app.world().resource::<DefIndex<Stat>>().queued_insertions(),
+        0
+    );
+}
+
+#[test]
+fn deferred_insertions_wait_for_their_own_def() {
+    let mut app = app(DefPlugin::default());
+
+    let mut commands = app.world_mut().commands();
+    let entity = commands
+        .spawn_empty()
+        .queue(InsertDef::by_name("mp", Stat { current: 1.0 }).deferred())
+        .queue(InsertDef::by_name("hp", Stat { current: 2.0 }).deferred())
+        .id();
+    app.world_mut().flush();
+
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    assert_eq!(value(&mut app, entity, "hp"), Some(2.0));
+    assert_eq!(
+        app.world().resource::<DefIndex<Stat>>().queued_insertions(),
+        1
+    );
+
+    let _mp = add(&mut app, StatAsset::new("mp", 5.0));
+    assert_eq!(value(&mut app, entity, "mp"), Some(1.0));
+}
@@ -0,0 +1,224 @@
+#![allow(dead_code)]
+
+use bevy::{
+    ecs::{component::ComponentId, entity::EntityMapper},
+    prelude::*,
+};
+use bevy_def::*;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    thread,
+    time::Duration,
+};
+
+#[derive(DefComponent, Reflect, Clone, Debug)]
+#[reflect(DefComponent)]
+#[def(asset = StatAsset, defname = defname, clone = "Clone")]
+pub struct Stat {
+    pub current: f32,
+}
+
+impl DefDefault for Stat {
+    fn from_asset(asset: &Self::Asset) -> Self {
+        Self {
+            current: asset.default.unwrap_or_default(),
+        }
+    }
+}
+
+impl DefPatch for Stat {
+    fn patch_layer(asset: &Self::Asset) -> Option<i32> {
+        asset.layer
+    }
+
+    fn merge(base: &Self::Asset, patches: &[&Self::Asset]) -> Self::Asset {
+        let mut merged = base.clone();
+        for patch in patches {
+            merged.default = patch.default.or(merged.default);
+        }
+        merged
+    }
+}
+
+impl DefInherit for Stat {
+    fn parent(asset: &Self::Asset) -> Option<&str> {
+        asset.parent.as_deref()
+    }
+
+    fn is_abstract(asset: &Self::Asset) -> bool {
+        asset.is_abstract
+    }
+
+    fn inherit(child: &Self::Asset, parent: &Self::Asset) -> Self::Asset {
+        StatAsset {
+            default: child.default.or(parent.default),
+            ..child.clone()
+        }
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct StatAsset {
+    pub defname: String,
+    pub default: Option<f32>,
+    pub parent: Option<String>,
+    pub is_abstract: bool,
+    pub layer: Option<i32>,
+}
+
+impl StatAsset {
+    pub fn new(defname: &str, default: f32) -> Self {
+        Self {
+            defname: defname.to_string(),
+            default: Some(default),
+            ..Default::default()
+        }
+    }
+
+    pub fn patch(defname: &str, layer: i32, default: f32) -> Self {
+        Self {
+            layer: Some(layer),
+            ..Self::new(defname, default)
+        }
+    }
+
+    pub fn child(defname: &str, parent: &str) -> Self {
+        Self {
+            defname: defname.to_string(),
+            parent: Some(parent.to_string()),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_default(self, default: f32) -> Self {
+        Self {
+            default: Some(default),
+            ..self
+        }
+    }
+
+    pub fn abstract_parent(self) -> Self {
+        Self {
+            is_abstract: true,
+            ..self
+        }
+    }
+}
+
+/// A def that refers to another entity.
+#[derive(DefComponent, Reflect, Clone, Debug)]
+#[def(asset = BondAsset, defname = defname, clone = "Clone", map_entities = Bond::map_target)]
+pub struct Bond {
+    pub target: Entity,
+}
+
+impl Bond {
+    fn map_target(this: &mut Self, mapper: &mut impl EntityMapper) {
+        this.target = mapper.get_mapped(this.target);
+    }
+}
+
+#[derive(Asset, TypePath, Debug, Clone)]
+pub struct BondAsset {
+    pub defname: String,
+}
+
+impl BondAsset {
+    pub fn new(defname: &str) -> Self {
+        Self {
+            defname: defname.to_string(),
+        }
+    }
+}
+
+pub fn app(plugin: DefPlugin<Stat>) -> App {
+    let mut app = App::new();
+    app.add_plugins((MinimalPlugins, AssetPlugin::default(), plugin));
+    app
+}
+
+/// An app that loads assets from a new directory for the test `name`, holding `files`.
+pub fn file_app(plugin: DefPlugin<Stat>, name: &str, files: &[(&str, &str)]) -> (App, PathBuf) {
+    let dir = std::env::temp_dir().join(format!("bevy_def_{name}_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    for &(path, contents) in files {
+        write_file(&dir, path, contents);
+    }
+
+    let mut app = App::new();
+    app.add_plugins((
+        MinimalPlugins,
+        AssetPlugin {
+            file_path: dir.to_string_lossy().into_owned(),
+            ..Default::default()
+        },
+        plugin,
+    ));
+    (app, dir)
+}
+
+pub fn write_file(dir: &Path, path: &str, contents: &str) {
+    let path = dir.join(path);
+    fs::create_dir_all(path.parent().unwrap()).unwrap();
+    fs::write(path, contents).unwrap();
+}
+
+/// Updates `app` until `done` holds, assets load in the background.
+pub fn update_until(app: &mut App, done: impl Fn(&App) -> bool) {
+    for _ in 0..500 {
+        app.update();
+        if done(app) {
+            return;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    panic!("timed out");
+}
+
+pub fn add(app: &mut App, asset: StatAsset) -> Handle<StatAsset> {
+    let handle = app.world_mut().resource_mut::<Assets<StatAsset>>().add(asset);
+    app.update();
+    handle
+}
+
+pub fn spawn(app: &mut App, name: &str, value: f32) -> Entity {
+    let mut commands = app.world_mut().commands();
+    let entity = commands
+        .spawn_empty()
+        .queue(InsertDef::by_name(name.to_string(), Stat { current: value }))
+        .id();
+    app.world_mut().flush();
+    entity
+}
+
+pub fn value(app: &mut App, entity: Entity, name: &str) -> Option<f32> {
+    let mut query = app.world_mut().query::<DefEntityRef<Stat>>();
+    let world = app.world();
+    query
+        .get(world, entity)
+        .ok()?
+        .find_ref(name)
+        .map(|stat| stat.value.current)
+}
+
+/// The default of the def `name` as the index sees it, after patches and inheritance.
+pub fn default_of(app: &App, name: &str) -> Option<f32> {
+    let world = app.world();
+    let (id, _) = world.resource::<DefIndex<Stat>>().find_by_name(name)?;
+    world.resource::<Assets<StatAsset>>().get(id)?.default
+}
+
+pub fn component_of(app: &App, name: &str) -> Option<ComponentId> {
+    let index = app.world().resource::<DefIndex<Stat>>();
+    index.find_by_name(name).map(|(_, component_id)| component_id)
+}
+
+pub fn events(app: &mut App) -> Vec<DefEvent<Stat>> {
+    app.world_mut()
+        .resource_mut::<Events<DefEvent<Stat>>>()
+        .drain()
+        .collect()
+}
@@ -0,0 +1,172 @@
+mod common;
+
+use bevy::{
+    ecs::{component::ComponentId, query::QueryBuilder, system::RunSystemOnce},
+    prelude::*,
+};
+use bevy_def::*;
+use common::*;
+
+#[derive(Component)]
+struct Speed;
+
+struct Hp;
+
+impl DefSelector<Stat> for Hp {
+    fn select(index: &DefIndex<Stat>) -> Option<ComponentId> {
+        index.find_by_name("hp").map(|(_, id)| id)
+    }
+}
+
+/// Picks a component that doesn't belong to the index.
+struct Foreign;
+
+impl DefSelector<Stat> for Foreign {
+    fn select(_index: &DefIndex<Stat>) -> Option<ComponentId> {
+        Some(ComponentId::new(0))
+    }
+}
+
+fn setup() -> (App, Handle<StatAsset>, Entity, Entity) {
+    let mut app = app(DefPlugin::default());
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let with = spawn(&mut app, "hp", 10.0);
+    app.world_mut().entity_mut(with).insert(Speed);
+    let without = app.world_mut().spawn(Speed).id();
+    (app, hp, with, without)
+}
+
+#[test]
+fn with_and_without_are_disjoint() {
+    let (mut app, _hp, with, without) = setup();
+
+    let speeds = app
+        .world_mut()
+        .run_system_once(
+            |mut with: Query<(Entity, &mut Speed), DefWith<Stat, Hp>>,
+             mut without: Query<(Entity, &mut Speed), DefWithout<Stat, Hp>>| {
+                let with: Vec<_> = with.iter_mut().map(|(entity, _)| entity).collect();
+                let without: Vec<_> = without.iter_mut().map(|(entity, _)| entity).collect();
+                (with, without)
+            },
+        )
+        .unwrap();
+
+    assert_eq!(speeds, (vec![with], vec![without]));
+}
+
+#[test]
+fn with_only_matches_archetypes_of_the_def() {
+    let (mut app, _hp, with, _) = setup();
+    let component_id = app
+        .world()
+        .resource::<DefIndex<Stat>>()
+        .find_by_name("hp")
+        .unwrap()
+        .1;
+
+    let mut query = app
+        .world_mut()
+        .query_filtered::<Entity, DefWith<Stat, Hp>>();
+    let world = app.world();
+    assert_eq!(query.iter(world).collect::<Vec<_>>(), [with]);
+    assert!(
+        query
+            .matched_archetypes()
+            .all(|archetype| world.archetypes()[archetype].contains(component_id))
+    );
+}
+
+#[test]
+fn defs_picked_at_runtime_with_a_builder() {
+    let (mut app, _hp, with, _) = setup();
+    let component_id = app
+        .world()
+        .resource::<DefIndex<Stat>>()
+        .find_by_name("hp")
+        .unwrap()
+        .1;
+
+    let mut query = QueryBuilder::<(Entity, DefEntityRef<Stat>)>::new(app.world_mut())
+        .with_id(component_id)
+        .build();
+    let world = app.world();
+    let found: Vec<_> = query
+        .iter(world)
+        .map(|(entity, stats)| (entity, stats.find_ref("hp").unwrap().value.current))
+        .collect();
+    assert_eq!(found, [(with, 10.0)]);
+}
+
+#[test]
+fn changed_ignores_components_of_other_types() {
+    let (mut app, _hp, _, _) = setup();
+
+    let mut query = app
+        .world_mut()
+        .query_filtered::<Entity, DefChanged<Stat, Foreign>>();
+    assert_eq!(query.iter(app.world()).count(), 0);
+}
+
+#[test]
+fn changed_picks_up_defs_registered_later() {
+    let mut app = app(DefPlugin::default());
+    app.init_resource::<Changed>();
+    app.add_systems(Update, count_changed);
+    app.update();
+
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    spawn(&mut app, "hp", 10.0);
+    app.update();
+
+    assert_eq!(app.world().resource::<Changed>().0, 1);
+}
+
+#[derive(Resource, Default)]
+struct Changed(usize);
+
+fn count_changed(query: Query<Entity, DefChanged<Stat, Hp>>, mut changed: ResMut<Changed>) {
+    changed.0 += query.iter().count();
+}
+
+struct Target;
+
+#[test]
+fn changed_follows_a_def_picked_at_runtime() {
+    let mut app = app(DefPlugin::default());
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let _mp = add(&mut app, StatAsset::new("mp", 5.0));
+    let hp = spawn(&mut app, "hp", 10.0);
+    let mp = spawn(&mut app, "mp", 5.0);
+
+    let target = String::from("mp");
+    let mut index = app.world_mut().resource_mut::<DefIndex<Stat>>();
+    let (_, component_id) = index.find_by_name(target.as_str()).unwrap();
+    index.pick::<Target>(component_id);
+
+    app.init_resource::<Changed>();
+    app.add_systems(Update, count_picked);
+    app.update();
+    assert_eq!(app.world().resource::<Changed>().0, 1);
+
+    set(&mut app, hp, "hp", 20.0);
+    app.update();
+    assert_eq!(app.world().resource::<Changed>().0, 1);
+
+    set(&mut app, mp, "mp", 10.0);
+    app.update();
+    assert_eq!(app.world().resource::<Changed>().0, 2);
+}
+
+fn count_picked(
+    query: Query<Entity, DefChanged<Stat, DefPicked<Target>>>,
+    mut changed: ResMut<Changed>,
+) {
+    changed.0 += query.iter().count();
+}
+
+fn set(app: &mut App, entity: Entity, name: &str, value: f32) {
+    let mut query = app.world_mut().query::<DefEntityMut<Stat>>();
+    let mut stats = query.get_mut(app.world_mut(), entity).unwrap();
+    stats.find_mut(name).unwrap().value.current = value;
+}
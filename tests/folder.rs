@@ -0,0 +1,49 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+#[derive(Resource, Default)]
+struct Ready(Vec<String>);
+
+fn count_ready(mut events: EventReader<DefFolderReady<Stat>>, mut ready: ResMut<Ready>) {
+    ready.0.extend(events.read().map(|event| event.path.clone()));
+}
+
+#[test]
+fn folders_are_ready_once_every_def_is_indexed() {
+    let plugin = DefPlugin::default()
+        .with_loader(&["stat.ron"], &[])
+        .with_file_loader(&["stats.ron"], &[])
+        .with_folder("defs");
+    let (mut app, _dir) = file_app(
+        plugin,
+        "folder",
+        &[
+            (
+                "defs/core.stats.ron",
+                r#"[(defname: "hp", default: Some(10.0)), (defname: "mp", default: Some(5.0))]"#,
+            ),
+            ("defs/luck.stat.ron", r#"(defname: "luck", default: Some(1.0))"#),
+        ],
+    );
+    app.init_resource::<Ready>();
+    app.add_systems(Update, count_ready);
+
+    update_until(&mut app, |app| {
+        app.world().resource::<DefFolders<Stat>>().is_ready()
+    });
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let folders = app.world().resource::<DefFolders<Stat>>();
+    let folder = folders.get("defs").unwrap();
+    assert_eq!(folder.registered(), 3);
+    assert_eq!(folder.pending(), Some(0));
+    assert_eq!(app.world().resource::<Ready>().0, ["defs"]);
+    for name in ["hp", "mp", "luck"] {
+        assert!(component_of(&app, name).is_some(), "{name}");
+    }
+}
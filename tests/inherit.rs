@@ -0,0 +1,108 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+fn inherit_app() -> App {
+    app(DefPlugin::default().with_inheritance())
+}
+
+#[test]
+fn children_resolve_through_their_chain() {
+    let mut app = inherit_app();
+    let _base = add(&mut app, StatAsset::new("base", 10.0).abstract_parent());
+    let _beast = add(&mut app, StatAsset::child("beast", "base").with_default(50.0));
+    let _wolf = add(&mut app, StatAsset::child("wolf", "beast"));
+    let _rat = add(&mut app, StatAsset::child("rat", "base"));
+
+    assert_eq!(component_of(&app, "base"), None);
+    assert_eq!(default_of(&app, "beast"), Some(50.0));
+    assert_eq!(default_of(&app, "wolf"), Some(50.0));
+    assert_eq!(default_of(&app, "rat"), Some(10.0));
+}
+
+#[test]
+fn children_wait_for_a_missing_parent() {
+    let mut app = inherit_app();
+    let wolf = add(&mut app, StatAsset::child("wolf", "beast"));
+    app.update();
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert!(!index.is_indexed(&wolf));
+    assert!(!index.is_loaded());
+    assert_eq!(component_of(&app, "wolf"), None);
+
+    let _beast = add(&mut app, StatAsset::new("beast", 50.0));
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert!(index.is_indexed(&wolf));
+    assert!(index.is_loaded());
+    assert_eq!(default_of(&app, "wolf"), Some(50.0));
+}
+
+#[test]
+fn resolved_children_send_their_events() {
+    let mut app = inherit_app();
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let _base = add(&mut app, StatAsset::new("base", 5.0).abstract_parent());
+    events(&mut app);
+
+    let _clash = add(&mut app, StatAsset::child("hp", "base"));
+
+    assert!(
+        events(&mut app)
+            .iter()
+            .any(|event| matches!(event, DefEvent::Collision { name, .. } if name == "hp"))
+    );
+    assert_eq!(default_of(&app, "hp"), Some(10.0));
+}
+
+#[test]
+fn children_follow_a_modified_parent() {
+    let mut app = inherit_app();
+    let beast = add(&mut app, StatAsset::new("beast", 50.0));
+    let _wolf = add(&mut app, StatAsset::child("wolf", "beast"));
+    let component_id = component_of(&app, "wolf");
+
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .get_mut(&beast)
+        .unwrap()
+        .default = Some(60.0);
+    app.update();
+
+    assert_eq!(default_of(&app, "wolf"), Some(60.0));
+    assert_eq!(component_of(&app, "wolf"), component_id);
+}
+
+#[test]
+fn cycles_are_not_registered() {
+    let mut app = inherit_app();
+    let _wolf = add(&mut app, StatAsset::child("wolf", "dog"));
+    let _dog = add(&mut app, StatAsset::child("dog", "wolf"));
+
+    assert_eq!(component_of(&app, "wolf"), None);
+    assert_eq!(component_of(&app, "dog"), None);
+}
+
+#[test]
+fn siblings_resolve_once_their_parent_arrives() {
+    let mut app = inherit_app();
+    let _wolf = add(&mut app, StatAsset::child("wolf", "beast"));
+    let _rat = add(&mut app, StatAsset::child("rat", "beast"));
+    let _cat = add(&mut app, StatAsset::child("cat", "beast"));
+    events(&mut app);
+
+    let _beast = add(&mut app, StatAsset::new("beast", 50.0).abstract_parent());
+    app.update();
+
+    assert!(
+        !events(&mut app)
+            .iter()
+            .any(|event| matches!(event, DefEvent::Collision { .. }))
+    );
+    for name in ["wolf", "rat", "cat"] {
+        assert_eq!(default_of(&app, name), Some(50.0), "{name}");
+    }
+}
@@ -0,0 +1,77 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+#[derive(DefComponent)]
+#[def(asset = StatAsset, defname = defname, key = "stats")]
+struct Attribute;
+
+#[test]
+fn derived_namespaces_are_stable() {
+    assert_eq!(Stat::key_namespace(), "Stat");
+    assert_eq!(Attribute::key_namespace(), "stats");
+    assert_eq!(DefKey::new::<Stat>("hp"), DefKey::from_parts("Stat", "hp"));
+}
+
+#[test]
+fn defs_are_found_by_key() {
+    let mut app = app(DefPlugin::default());
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    let key = DefKey::from_parts("Stat", "hp");
+    assert_eq!(index.find_by_key(key).map(|(id, _)| id), Some(hp.id()));
+    assert_eq!(index.asset_key(&hp), Some(key));
+}
+
+/// Two defnames whose keys collide in the `Stat` namespace.
+const COLLIDING: [&str; 2] = ["86a10db620748d97", "db600a540bfca59c"];
+
+#[test]
+fn colliding_keys_stay_with_the_first_def() {
+    assert_eq!(
+        DefKey::new::<Stat>(COLLIDING[0]),
+        DefKey::new::<Stat>(COLLIDING[1])
+    );
+
+    let mut app = app(DefPlugin::default());
+    let first = add(&mut app, StatAsset::new(COLLIDING[0], 1.0));
+    let second = add(&mut app, StatAsset::new(COLLIDING[1], 2.0));
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    let key = DefKey::new::<Stat>(COLLIDING[0]);
+    assert_eq!(index.find_by_key(key).map(|(id, _)| id), Some(first.id()));
+    assert_eq!(index.asset_key(&second), None);
+    assert!(index.find_by_name(COLLIDING[1]).is_some());
+
+    // Removing the def without the key leaves the key alone.
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .remove(&second);
+    app.update();
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert_eq!(index.find_by_key(key).map(|(id, _)| id), Some(first.id()));
+}
+
+#[test]
+fn renamed_defs_keep_a_colliding_key() {
+    let mut app = app(DefPlugin::default());
+    let first = add(&mut app, StatAsset::new(COLLIDING[0], 1.0));
+    let second = add(&mut app, StatAsset::new("hp", 2.0));
+
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .get_mut(&second)
+        .unwrap()
+        .defname = COLLIDING[1].to_string();
+    app.update();
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    let key = DefKey::new::<Stat>(COLLIDING[0]);
+    assert_eq!(index.find_by_key(key).map(|(id, _)| id), Some(first.id()));
+    assert_eq!(index.asset_key(&second), None);
+    assert_eq!(index.find_by_key(DefKey::new::<Stat>("hp")), None);
+}
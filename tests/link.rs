@@ -0,0 +1,81 @@
+mod common;
+
+use bevy::{ecs::system::RunSystemOnce, prelude::*};
+use bevy_def::*;
+use common::*;
+
+#[derive(DefComponent)]
+#[def(asset = SkillAsset, defname = defname)]
+struct Skill;
+
+impl DefLinks<Stat> for Skill {
+    fn links(asset: &Self::Asset) -> Vec<&DefLink<Stat>> {
+        vec![&asset.governing_stat]
+    }
+}
+
+#[derive(Asset, TypePath)]
+struct SkillAsset {
+    defname: String,
+    governing_stat: DefLink<Stat>,
+}
+
+fn governing(app: &mut App, link: &DefLink<Stat>) -> Option<f32> {
+    let link = link.clone();
+    app.world_mut()
+        .run_system_once(move |stats: DefParam<Stat>| {
+            stats
+                .get(&link)
+                .ok()
+                .and_then(|(_, asset)| asset.default)
+        })
+        .unwrap()
+}
+
+#[test]
+fn links_follow_the_def_they_name() {
+    let mut app = app(DefPlugin::default());
+    let link = DefLink::<Stat>::new("hp");
+
+    assert_eq!(governing(&mut app, &link), None);
+
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    assert_eq!(governing(&mut app, &link), Some(10.0));
+
+    // The asset is renamed, so the name is free and the cached link resolves again.
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .get_mut(&hp)
+        .unwrap()
+        .defname = "health".to_string();
+    app.update();
+    assert_eq!(governing(&mut app, &link), None);
+
+    let _other = add(&mut app, StatAsset::new("hp", 20.0));
+    assert_eq!(governing(&mut app, &link), Some(20.0));
+}
+
+#[test]
+fn links_in_assets_resolve_through_the_param() {
+    let mut app = app(DefPlugin::default());
+    app.add_plugins(DefPlugin::<Skill>::default().with_links::<Stat>());
+
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let fencing = app
+        .world_mut()
+        .resource_mut::<Assets<SkillAsset>>()
+        .add(SkillAsset {
+            defname: "fencing".to_string(),
+            governing_stat: DefLink::new("hp"),
+        });
+    app.update();
+
+    let link = app
+        .world()
+        .resource::<Assets<SkillAsset>>()
+        .get(&fencing)
+        .unwrap()
+        .governing_stat
+        .clone();
+    assert_eq!(governing(&mut app, &link), Some(10.0));
+}
@@ -0,0 +1,46 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+const STATS: &str = r#"[
+    (defname: "hp", default: Some(10.0)),
+    (defname: "mp", default: Some(5.0)),
+]"#;
+
+#[test]
+fn files_register_every_def_in_them() {
+    let plugin = DefPlugin::default().with_file_loader(&["stats.ron"], &[]);
+    let (mut app, dir) = file_app(plugin, "loader", &[("core.stats.ron", STATS)]);
+
+    let file: Handle<DefFile<StatAsset>> =
+        app.world().resource::<AssetServer>().load("core.stats.ron");
+    update_until(&mut app, |app| component_of(app, "mp").is_some());
+
+    assert_eq!(default_of(&app, "hp"), Some(10.0));
+    assert_eq!(default_of(&app, "mp"), Some(5.0));
+    let (hp, _) = app
+        .world()
+        .resource::<DefIndex<Stat>>()
+        .find_by_name("hp")
+        .unwrap();
+    let path = app.world().resource::<AssetServer>().get_path(hp).unwrap();
+    assert_eq!(path.label(), Some("hp"));
+
+    let components = (component_of(&app, "hp"), component_of(&app, "mp"));
+    write_file(
+        &dir,
+        "core.stats.ron",
+        &STATS.replace("10.0", "20.0").replace("5.0", "6.0"),
+    );
+    app.world().resource::<AssetServer>().reload("core.stats.ron");
+    update_until(&mut app, |app| default_of(app, "mp") == Some(6.0));
+
+    assert_eq!(default_of(&app, "hp"), Some(20.0));
+    assert_eq!(
+        (component_of(&app, "hp"), component_of(&app, "mp")),
+        components
+    );
+    drop(file);
+}
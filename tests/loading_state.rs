@@ -0,0 +1,55 @@
+mod common;
+
+use bevy::{prelude::*, state::app::StatesPlugin};
+use bevy_def::*;
+use common::*;
+
+#[derive(States, Clone, PartialEq, Eq, Hash, Debug, Default)]
+enum AppState {
+    #[default]
+    Loading,
+    Running,
+}
+
+fn loading_app() -> App {
+    let mut app = app(DefPlugin::default().with_loading_state(AppState::Loading, AppState::Running));
+    app.add_plugins(StatesPlugin);
+    app.init_state::<AppState>();
+    app
+}
+
+fn state(app: &App) -> &AppState {
+    app.world().resource::<State<AppState>>().get()
+}
+
+#[test]
+fn waits_for_requested_defs() {
+    let mut app = loading_app();
+    let handle = app
+        .world()
+        .resource::<Assets<StatAsset>>()
+        .reserve_handle();
+    app.world_mut()
+        .resource_mut::<DefIndex<Stat>>()
+        .request(&handle);
+
+    for _ in 0..3 {
+        app.update();
+    }
+    assert_eq!(state(&app), &AppState::Loading);
+
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .insert(&handle, StatAsset::new("hp", 10.0));
+    app.update();
+    app.update();
+    assert_eq!(state(&app), &AppState::Running);
+}
+
+#[test]
+fn moves_on_without_requests() {
+    let mut app = loading_app();
+    app.update();
+    app.update();
+    assert_eq!(state(&app), &AppState::Running);
+}
@@ -0,0 +1,108 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+fn patch_app() -> App {
+    app(DefPlugin::default().with_patches())
+}
+
+fn remove(app: &mut App, handle: &Handle<StatAsset>) {
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .remove(handle);
+    app.update();
+}
+
+#[test]
+fn patch_added_after_its_base() {
+    let mut app = patch_app();
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let component_id = component_of(&app, "hp");
+    let entity = spawn(&mut app, "hp", 1.0);
+
+    let _patch = add(&mut app, StatAsset::patch("hp", 1, 20.0));
+
+    assert_eq!(default_of(&app, "hp"), Some(20.0));
+    assert_eq!(component_of(&app, "hp"), component_id);
+    assert_eq!(value(&mut app, entity, "hp"), Some(1.0));
+}
+
+#[test]
+fn patch_added_before_its_base() {
+    let mut app = patch_app();
+    let _patch = add(&mut app, StatAsset::patch("hp", 1, 20.0));
+    assert_eq!(component_of(&app, "hp"), None);
+
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+
+    assert_eq!(default_of(&app, "hp"), Some(20.0));
+}
+
+#[test]
+fn patches_apply_by_layer() {
+    let mut app = patch_app();
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let _high = add(&mut app, StatAsset::patch("hp", 2, 30.0));
+    let _low = add(&mut app, StatAsset::patch("hp", 1, 20.0));
+
+    assert_eq!(default_of(&app, "hp"), Some(30.0));
+}
+
+#[test]
+fn removed_patch_restores_the_base() {
+    let mut app = patch_app();
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let component_id = component_of(&app, "hp");
+    let patch = add(&mut app, StatAsset::patch("hp", 1, 20.0));
+
+    remove(&mut app, &patch);
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert_eq!(index.find_by_name("hp"), Some((hp.id(), component_id.unwrap())));
+    assert_eq!(default_of(&app, "hp"), Some(10.0));
+}
+
+#[test]
+fn modified_base_is_merged_again() {
+    let mut app = patch_app();
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    // A patch that leaves the default to the base.
+    let _patch = add(
+        &mut app,
+        StatAsset {
+            default: None,
+            ..StatAsset::patch("hp", 1, 0.0)
+        },
+    );
+    assert_eq!(default_of(&app, "hp"), Some(10.0));
+
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .get_mut(&hp)
+        .unwrap()
+        .default = Some(15.0);
+    app.update();
+
+    assert_eq!(default_of(&app, "hp"), Some(15.0));
+}
+
+#[test]
+fn removed_base_keeps_its_patches() {
+    let mut app = patch_app();
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let component_id = component_of(&app, "hp");
+    let _patch = add(&mut app, StatAsset::patch("hp", 1, 20.0));
+
+    remove(&mut app, &hp);
+
+    assert_eq!(component_of(&app, "hp"), None);
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert!(index.is_orphaned(component_id.unwrap()));
+
+    // The def comes back with its component and its patch.
+    let _hp = add(&mut app, StatAsset::new("hp", 12.0));
+    assert_eq!(component_of(&app, "hp"), component_id);
+    assert_eq!(default_of(&app, "hp"), Some(20.0));
+}
@@ -0,0 +1,96 @@
+mod common;
+
+use bevy::{ecs::component::ComponentId, prelude::*};
+use bevy_def::*;
+use common::*;
+
+struct Hp;
+
+impl DefSelector<Stat> for Hp {
+    fn select(index: &DefIndex<Stat>) -> Option<ComponentId> {
+        index.find_by_name("hp").map(|(_, id)| id)
+    }
+}
+
+#[derive(Component)]
+struct Marker;
+
+fn regen(mut query: DefQueryMut<Stat, Hp>) {
+    for (_, mut hp) in query.iter_mut() {
+        hp.value.current += 1.0;
+    }
+}
+
+#[test]
+fn only_visits_entities_with_the_def() {
+    let mut app = app(DefPlugin::default());
+    // The system is built before the def and the entities exist.
+    app.add_systems(Update, regen);
+    app.update();
+
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let _mp = add(&mut app, StatAsset::new("mp", 5.0));
+
+    let plain = spawn(&mut app, "hp", 1.0);
+    let marked = spawn(&mut app, "hp", 2.0);
+    app.world_mut().entity_mut(marked).insert(Marker);
+    let other = spawn(&mut app, "mp", 3.0);
+    app.world_mut().spawn(Marker);
+
+    app.update();
+
+    assert_eq!(value(&mut app, plain, "hp"), Some(2.0));
+    assert_eq!(value(&mut app, marked, "hp"), Some(3.0));
+    assert_eq!(value(&mut app, other, "mp"), Some(3.0));
+
+    let visited = app
+        .world_mut()
+        .run_system_cached(|query: DefQuery<Stat, Hp>| {
+            let mut visited: Vec<_> = query.iter().map(|(entity, _)| entity).collect();
+            visited.sort();
+            visited
+        })
+        .unwrap();
+    let mut expected = vec![plain, marked];
+    expected.sort();
+    assert_eq!(visited, expected);
+}
+
+#[derive(Resource)]
+struct Watched {
+    entity: Entity,
+    added: Vec<Option<bool>>,
+}
+
+fn watch(mut query: DefQueryMut<Stat, Hp>, mut watched: ResMut<Watched>) {
+    let added = query.get_mut(watched.entity).map(|hp| hp.value.is_added());
+    watched.added.push(added);
+}
+
+fn insert_once(mut commands: Commands, watched: Res<Watched>, mut done: Local<bool>) {
+    if !std::mem::replace(&mut *done, true) {
+        commands
+            .entity(watched.entity)
+            .queue(InsertDef::by_name("hp", Stat { current: 1.0 }));
+    }
+}
+
+#[test]
+fn get_mut_uses_the_ticks_of_the_system() {
+    let mut app = app(DefPlugin::default());
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let entity = app.world_mut().spawn_empty().id();
+    app.insert_resource(Watched {
+        entity,
+        added: Vec::new(),
+    });
+    // Inserted after `watch` ran, so only its next run sees the def as added.
+    app.add_systems(Update, (watch, insert_once).chain());
+
+    for _ in 0..3 {
+        app.update();
+    }
+
+    let added = &app.world().resource::<Watched>().added;
+    assert_eq!(added, &[None, Some(true), Some(false)]);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+#[derive(Component)]
+struct Speed(f32);
+
+#[derive(Resource, Default)]
+struct Seen(Vec<f32>);
+
+fn def_first(mut query: Query<(DefEntityMut<Stat>, &Speed)>, mut seen: ResMut<Seen>) {
+    for (mut stats, speed) in &mut query {
+        let mut hp = stats.find_mut("hp").unwrap();
+        hp.value.current += speed.0;
+        seen.0.push(hp.value.current);
+    }
+}
+
+fn def_last(mut query: Query<(&Speed, DefEntityMut<Stat>)>, mut seen: ResMut<Seen>) {
+    for (speed, mut stats) in &mut query {
+        let mut hp = stats.find_mut("hp").unwrap();
+        hp.value.current += speed.0;
+        seen.0.push(hp.value.current);
+    }
+}
+
+fn run<M>(system: impl IntoScheduleConfigs<bevy::ecs::system::ScheduleSystem, M>) -> Vec<f32> {
+    let mut app = app(DefPlugin::default());
+    app.init_resource::<Seen>();
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+
+    // `Speed` is registered after the def plugin reserved its components.
+    app.add_systems(Update, system);
+    let entity = spawn(&mut app, "hp", 10.0);
+    app.world_mut().entity_mut(entity).insert(Speed(1.0));
+
+    app.update();
+    app.update();
+    app.world_mut().remove_resource::<Seen>().unwrap().0
+}
+
+#[test]
+fn def_access_before_component_registered_later() {
+    assert_eq!(run(def_first), [11.0, 12.0]);
+}
+
+#[test]
+fn def_access_after_component_registered_later() {
+    assert_eq!(run(def_last), [11.0, 12.0]);
+}
+
+#[test]
+fn defs_beyond_capacity_are_registered() {
+    let mut app = app(DefPlugin::default().with_capacity(1));
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let _mp = add(&mut app, StatAsset::new("mp", 5.0));
+
+    let entity = spawn(&mut app, "mp", 7.0);
+    assert_eq!(value(&mut app, entity, "mp"), Some(7.0));
+}
+
+#[test]
+#[should_panic]
+fn defs_beyond_capacity_panic_once_queries_are_built() {
+    let mut app = app(DefPlugin::default().with_capacity(1));
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    app.world_mut().query::<DefEntityRef<Stat>>();
+
+    let _mp = add(&mut app, StatAsset::new("mp", 5.0));
+}
@@ -0,0 +1,62 @@
+mod common;
+
+use bevy::{ecs::component::ComponentId, prelude::*};
+use bevy_def::*;
+use common::*;
+
+fn reflect_def(app: &App, component_id: ComponentId) -> ReflectDefComponent {
+    let world = app.world();
+    let type_id = world
+        .resource::<DefComponentTypes>()
+        .get(component_id)
+        .unwrap();
+    let registry = world.resource::<AppTypeRegistry>().read();
+    registry
+        .get_type_data::<ReflectDefComponent>(type_id)
+        .unwrap()
+        .clone()
+}
+
+#[test]
+fn defs_are_read_and_written_through_reflection() {
+    let mut app = app(DefPlugin::default());
+    app.register_type::<Stat>();
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let entity = spawn(&mut app, "hp", 7.0);
+    let component_id = component_of(&app, "hp").unwrap();
+
+    let reflect = reflect_def(&app, component_id);
+    assert_eq!(reflect.component_id(app.world(), "hp"), Some(component_id));
+
+    let stat = reflect.reflect(app.world(), entity, component_id).unwrap();
+    assert_eq!(stat.downcast_ref::<Stat>().unwrap().current, 7.0);
+
+    let mut entity_mut = app.world_mut().entity_mut(entity);
+    let mut stat = reflect.reflect_mut(&mut entity_mut, component_id).unwrap();
+    stat.downcast_mut::<Stat>().unwrap().current = 8.0;
+    assert_eq!(value(&mut app, entity, "hp"), Some(8.0));
+
+    let other = app.world_mut().spawn_empty().id();
+    let mut other_mut = app.world_mut().entity_mut(other);
+    reflect
+        .insert(&mut other_mut, component_id, &Stat { current: 3.0 })
+        .unwrap();
+    assert_eq!(value(&mut app, other, "hp"), Some(3.0));
+}
+
+#[test]
+fn reflection_checks_def_components() {
+    let mut app = app(DefPlugin::default());
+    app.register_type::<Stat>();
+    let _hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let reflect = reflect_def(&app, component_of(&app, "hp").unwrap());
+
+    let transform = app.world_mut().register_component::<Transform>();
+    let mut entity = app.world_mut().spawn_empty();
+    assert!(!reflect.contains(entity.world(), transform));
+    assert!(
+        reflect
+            .insert(&mut entity, transform, &Stat { current: 3.0 })
+            .is_err()
+    );
+}
@@ -0,0 +1,63 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+fn remove(app: &mut App, handle: Handle<StatAsset>) {
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .remove(&handle);
+    app.update();
+}
+
+#[test]
+fn orphaned_defs_stay_on_entities() {
+    let mut app = app(DefPlugin::default());
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let entity = spawn(&mut app, "hp", 7.0);
+    let component_id = component_of(&app, "hp").unwrap();
+
+    remove(&mut app, hp);
+
+    let index = app.world().resource::<DefIndex<Stat>>();
+    assert!(index.is_orphaned(component_id));
+    assert_eq!(index.find_by_name("hp"), None);
+    assert!(app.world().entity(entity).contains_id(component_id));
+}
+
+#[test]
+fn removed_defs_are_removed_from_entities() {
+    let mut app = app(DefPlugin::default().with_removal_policy(DefRemovalPolicy::RemoveComponents));
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let _mp = add(&mut app, StatAsset::new("mp", 5.0));
+    let entity = spawn(&mut app, "hp", 7.0);
+    app.world_mut()
+        .commands()
+        .entity(entity)
+        .queue(InsertDef::by_name("mp", Stat { current: 3.0 }));
+    app.world_mut().flush();
+    let component_id = component_of(&app, "hp").unwrap();
+    events(&mut app);
+
+    let id = hp.id();
+    remove(&mut app, hp);
+
+    assert!(!app.world().entity(entity).contains_id(component_id));
+    assert_eq!(value(&mut app, entity, "mp"), Some(3.0));
+    assert_eq!(component_of(&app, "hp"), None);
+    assert!(
+        events(&mut app)
+            .iter()
+            .any(|event| matches!(event, DefEvent::Removed { id: removed, name, .. } if *removed == id && name == "hp"))
+    );
+}
+
+#[test]
+#[should_panic]
+fn removals_can_panic() {
+    let mut app = app(DefPlugin::default().with_removal_policy(DefRemovalPolicy::Panic));
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+
+    remove(&mut app, hp);
+}
@@ -0,0 +1,33 @@
+mod common;
+
+use bevy::prelude::*;
+use bevy_def::*;
+use common::*;
+
+#[test]
+fn renamed_defs_keep_their_component() {
+    let mut app = app(DefPlugin::default());
+    let hp = add(&mut app, StatAsset::new("hp", 10.0));
+    let entity = spawn(&mut app, "hp", 7.0);
+    let component_id = component_of(&app, "hp").unwrap();
+    events(&mut app);
+
+    app.world_mut()
+        .resource_mut::<Assets<StatAsset>>()
+        .get_mut(&hp)
+        .unwrap()
+        .defname = "health".to_string();
+    app.update();
+
+    let renamed = events(&mut app).into_iter().any(|event| {
+        matches!(
+            event,
+            DefEvent::Renamed { id, component_id: renamed, old, new }
+                if id == hp.id() && renamed == component_id && old == "hp" && new == "health"
+        )
+    });
+    assert!(renamed);
+    assert_eq!(component_of(&app, "hp"), None);
+    assert_eq!(component_of(&app, "health"), Some(component_id));
+    assert_eq!(value(&mut app, entity, "health"), Some(7.0));
+}
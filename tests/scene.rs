@@ -0,0 +1,78 @@
+mod common;
+
+use bevy::{
+    ecs::entity::EntityHashMap,
+    prelude::*,
+    scene::{DynamicSceneBuilder, serde::SceneDeserializer},
+};
+use bevy_def::*;
+use common::*;
+use serde::de::DeserializeSeed;
+
+fn scene_app(names: &[&str]) -> (App, Vec<Handle<StatAsset>>, Handle<BondAsset>) {
+    let mut app = app(DefPlugin::default());
+    app.add_plugins((
+        DefPlugin::<Bond>::default(),
+        DefScenePlugin::<Stat>::default(),
+        DefScenePlugin::<Bond>::default(),
+    ));
+    let stats = names
+        .iter()
+        .map(|name| add(&mut app, StatAsset::new(name, 0.0)))
+        .collect();
+    let owner = app
+        .world_mut()
+        .resource_mut::<Assets<BondAsset>>()
+        .add(BondAsset::new("owner"));
+    app.update();
+    (app, stats, owner)
+}
+
+#[test]
+fn scenes_keep_defs_by_name() {
+    let (mut source, _stats, _owner) = scene_app(&["hp", "mp"]);
+    let pet = spawn(&mut source, "mp", 3.0);
+    let hero = spawn(&mut source, "hp", 7.0);
+    source
+        .world_mut()
+        .commands()
+        .entity(hero)
+        .queue(InsertDef::by_name("owner", Bond { target: pet }));
+    source.world_mut().flush();
+
+    let world = source.world();
+    let mut scene = DynamicSceneBuilder::from_world(world)
+        .extract_entities([hero, pet].into_iter())
+        .build();
+    extract_scene_defs::<Stat>(&mut scene, world);
+    extract_scene_defs::<Bond>(&mut scene, world);
+    let registry = world.resource::<AppTypeRegistry>().clone();
+    let ron = scene.serialize(&registry.read()).unwrap();
+
+    // Another run that added the defs in another order.
+    let (mut target, _stats, _owner) = scene_app(&["mp", "hp"]);
+    assert_ne!(component_of(&source, "hp"), component_of(&target, "hp"));
+
+    target.world_mut().spawn_batch([(), (), ()]);
+
+    let registry = target.world().resource::<AppTypeRegistry>().clone();
+    let mut deserializer = ron::de::Deserializer::from_str(&ron).unwrap();
+    let scene = SceneDeserializer {
+        type_registry: &registry.read(),
+    }
+    .deserialize(&mut deserializer)
+    .unwrap();
+    let mut entity_map = EntityHashMap::default();
+    scene
+        .write_to_world(target.world_mut(), &mut entity_map)
+        .unwrap();
+    target.world_mut().flush();
+
+    assert_ne!(entity_map[&pet], pet);
+    let (hero, pet) = (entity_map[&hero], entity_map[&pet]);
+    assert_eq!(value(&mut target, hero, "hp"), Some(7.0));
+    assert_eq!(value(&mut target, pet, "mp"), Some(3.0));
+    let mut query = target.world_mut().query::<DefEntityRef<Bond>>();
+    let bonds = query.get(target.world(), hero).unwrap();
+    assert_eq!(bonds.find_ref("owner").unwrap().value.target, pet);
+}